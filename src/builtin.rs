@@ -0,0 +1,247 @@
+//! Commands provided by yalla itself rather than the Yallafile
+//!
+//! Their names start with `:` so they can never collide with a task.
+use std::{fs, io};
+
+use anyhow::{Context, anyhow, bail};
+use clap::builder::PossibleValuesParser;
+use clap::{Arg, ArgAction, ArgGroup, ArgMatches, Command, value_parser};
+use clap_complete::env::Shells;
+
+use crate::check;
+use crate::diagnostic::Diagnostic;
+use crate::docs;
+use crate::error::YallaError;
+use crate::history;
+use crate::list::{self, Format};
+use crate::model::{self, CmdNode};
+use crate::run::{self, Options};
+use crate::toml::YALLAFILE;
+use crate::{pick, schema};
+
+pub const PREFIX: char = ':';
+
+pub fn is_builtin(name: &str) -> bool {
+    name.starts_with(PREFIX)
+}
+
+/// Add the built-in commands to the root command. They are kept out of the
+/// task list and named in a footer instead.
+pub fn with_builtins(cmd: Command) -> Command {
+    let builtins = commands();
+    let names: Vec<&str> = builtins.iter().map(|c| c.get_name()).collect();
+    let footer = format!(
+        "Built-in commands: {} (see `yalla <command> --help`)",
+        names.join(", ")
+    );
+    let footer = match cmd.get_after_help() {
+        Some(existing) => format!("{existing}\n\n{footer}"),
+        None => footer,
+    };
+    cmd.after_help(footer)
+        .subcommands(builtins.into_iter().map(|c| c.hide(true)))
+}
+
+fn commands() -> Vec<Command> {
+    vec![
+        Command::new(":list")
+            .about("List tasks as a tree, dotted paths or JSON")
+            .arg(
+                Arg::new("path")
+                    .num_args(0..)
+                    .help("Only list tasks below this namespace"),
+            )
+            .arg(
+                Arg::new("tree")
+                    .long("tree")
+                    .action(ArgAction::SetTrue)
+                    .help("Show a tree with descriptions (default)"),
+            )
+            .arg(
+                Arg::new("flat")
+                    .long("flat")
+                    .action(ArgAction::SetTrue)
+                    .help("Show one dotted path per line"),
+            )
+            .arg(
+                Arg::new("json")
+                    .long("json")
+                    .action(ArgAction::SetTrue)
+                    .help("Show name, path, description, cmd and kind as JSON"),
+            )
+            .group(ArgGroup::new("format").args(["tree", "flat", "json"]))
+            .arg(
+                Arg::new("all")
+                    .long("all")
+                    .action(ArgAction::SetTrue)
+                    .help("Include hidden and private tasks"),
+            ),
+        Command::new(":pick")
+            .short_flag('i')
+            .long_flag("interactive")
+            .about("Pick a task from a fuzzy-filtered list, then run it (also `yalla -i`)"),
+        Command::new(":history")
+            .about("Show recently run tasks, or run one of them again")
+            .arg(
+                Arg::new("limit")
+                    .short('n')
+                    .long("limit")
+                    .value_parser(value_parser!(usize))
+                    .default_value("20")
+                    .help("Show this many runs"),
+            )
+            .arg(
+                Arg::new("rerun")
+                    .long("rerun")
+                    .value_name("N")
+                    .value_parser(value_parser!(usize))
+                    .help("Run the Nth most recent task again, with the same arguments"),
+            ),
+        Command::new(":dump")
+            .about("Print the resolved task tree as versioned JSON, with source locations"),
+        Command::new(":schema").about("Print the JSON Schema of the Yallafile format"),
+        Command::new(":docs")
+            .about("Render the tasks as Markdown or a man page")
+            .arg(
+                Arg::new("format")
+                    .long("format")
+                    .value_parser(["markdown", "man"])
+                    .default_value("markdown"),
+            )
+            .arg(
+                Arg::new("check")
+                    .long("check")
+                    .value_name("FILE")
+                    .help("Fail if FILE differs from the rendered output instead of printing it"),
+            ),
+        Command::new(":check")
+            .about("Report problems in the Yallafile without running anything")
+            .arg(
+                Arg::new("format")
+                    .long("format")
+                    .value_parser(["human", "json", "sarif"])
+                    .default_value("human"),
+            ),
+        Command::new(":completions")
+            .about("Print a shell completion script that asks yalla for task names")
+            .arg(
+                Arg::new("shell")
+                    .required(true)
+                    .value_parser(PossibleValuesParser::new(Shells::builtins().names())),
+            ),
+    ]
+}
+
+pub fn run(root: &CmdNode, name: &str, m: &ArgMatches, opts: Options) -> anyhow::Result<()> {
+    match name {
+        ":list" => {
+            let path: Vec<String> = m
+                .get_many::<String>("path")
+                .map(|p| p.flat_map(|s| model::split_path(s)).collect())
+                .unwrap_or_default();
+            let path =
+                model::canonical_path(root, &path).ok_or_else(|| YallaError::UnknownTask {
+                    path: path.join("."),
+                })?;
+            let format = if m.get_flag("json") {
+                Format::Json
+            } else if m.get_flag("flat") {
+                Format::Flat
+            } else {
+                Format::Tree
+            };
+            print!("{}", list::render(root, &path, format, m.get_flag("all")));
+            Ok(())
+        }
+        ":pick" => {
+            let (path, args) = pick::pick(root)?;
+            let node = model::find_node(root, &path).expect("picked from the tree");
+            Ok(run::task(node, &path, &args, opts)?)
+        }
+        ":history" => {
+            let runs = history::load()?;
+            let Some(&n) = m.get_one::<usize>("rerun") else {
+                let limit = *m.get_one::<usize>("limit").expect("has a default");
+                print!("{}", history::render(&runs, limit));
+                return Ok(());
+            };
+            let past = history::nth_recent(&runs, n)?;
+            let path = model::split_path(&past.task);
+            // The task may have been made private since it ran
+            run::refuse_private(root, &path)?;
+            let node = model::find_node(root, &path)
+                .filter(|n| n.cmd.is_some())
+                .ok_or_else(|| YallaError::UnknownTask {
+                    path: past.task.clone(),
+                })?;
+            eprintln!("yalla: rerunning {}", past.task);
+            Ok(run::task(node, &path, &past.args, opts)?)
+        }
+        ":dump" => {
+            print!("{}", list::dump(root));
+            Ok(())
+        }
+        ":schema" => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&schema::yallafile_schema())?
+            );
+            Ok(())
+        }
+        ":docs" => {
+            let format = match m.get_one::<String>("format").map(String::as_str) {
+                Some("man") => docs::Format::Man,
+                _ => docs::Format::Markdown,
+            };
+            let rendered = docs::render(root, format);
+            match m.get_one::<String>("check") {
+                Some(file) => {
+                    let committed = fs::read_to_string(file)
+                        .with_context(|| format!("Failed to read {file}"))?;
+                    if committed != rendered {
+                        bail!(
+                            "{file} is stale; regenerate it with `yalla :docs --format {} > {file}`",
+                            format.name()
+                        );
+                    }
+                }
+                None => print!("{rendered}"),
+            }
+            Ok(())
+        }
+        ":check" => {
+            let format = match m.get_one::<String>("format").map(String::as_str) {
+                Some("json") => check::Format::Json,
+                Some("sarif") => check::Format::Sarif,
+                _ => check::Format::Human,
+            };
+            let problems = check::check(YALLAFILE);
+            print!("{}", check::render(&problems, YALLAFILE, format));
+            let errors = problems
+                .iter()
+                .filter(|p| p.severity == check::Severity::Error)
+                .count();
+            if errors > 0 {
+                return Err(YallaError::Invalid(Diagnostic::new(format!(
+                    "{YALLAFILE} has {errors} error(s)"
+                )))
+                .into());
+            }
+            Ok(())
+        }
+        ":completions" => {
+            let shell = m.get_one::<String>("shell").expect("required");
+            let shells = Shells::builtins();
+            let completer = shells.completer(shell).expect("validated by clap");
+            // The script calls back into this binary, so tasks are always current. argv[0]
+            // may be a bare name or a relative path that means nothing from another
+            // directory, so use the absolute path of the running executable.
+            let bin = std::env::current_exe()
+                .map(|p| p.to_string_lossy().into_owned())
+                .unwrap_or_else(|_| "yalla".to_string());
+            completer.write_registration("COMPLETE", "yalla", "yalla", &bin, &mut io::stdout())?;
+            Ok(())
+        }
+        _ => Err(anyhow!("Unknown built-in command: {name}")),
+    }
+}
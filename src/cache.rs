@@ -0,0 +1,220 @@
+//! Up-to-date checks for tasks declaring `sources` (and optionally `outputs`)
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::error::YallaError;
+use crate::model::CmdNode;
+
+const CACHE_DIR: &str = ".yalla/cache";
+
+/// Outcome of an up-to-date check, with a human-readable reason for `-v`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Freshness {
+    Fresh(String),
+    Stale(String),
+}
+
+/// Decide whether `node` needs to run `cmd`, its command with any arguments appended. `key`
+/// identifies the task in the cache (its dotted path).
+pub fn check(node: &CmdNode, key: &str, cmd: &str, force: bool) -> Result<Freshness, YallaError> {
+    if force {
+        return Ok(Freshness::Stale("--force given".to_string()));
+    }
+    if node.sources.is_empty() {
+        return Ok(Freshness::Stale("no sources declared".to_string()));
+    }
+    // Otherwise a mistyped pattern would leave only `cmd` in the digest, and the task would
+    // be skipped for good after its first run
+    if let Some(pattern) = node.sources.iter().find(|p| matches_nothing(p)) {
+        return Ok(Freshness::Stale(format!(
+            "source '{pattern}' matches no files"
+        )));
+    }
+    let sources = expand(&node.sources)?;
+
+    // Arguments can change what the task produces, so only the digest, which covers them,
+    // can tell whether a run with arguments is up to date
+    if !node.outputs.is_empty() && node.cmd.as_deref() == Some(cmd) {
+        let mut outputs = Vec::new();
+        for pattern in &node.outputs {
+            let found = expand(std::slice::from_ref(pattern))?;
+            if found.is_empty() {
+                return Ok(Freshness::Stale(format!("output '{pattern}' is missing")));
+            }
+            outputs.extend(found);
+        }
+        let newest_source = newest(&sources)?;
+        let oldest_output = oldest(&outputs)?;
+        if let (Some(src), Some(out)) = (newest_source, oldest_output)
+            && out > src
+        {
+            return Ok(Freshness::Fresh(
+                "outputs are newer than sources".to_string(),
+            ));
+        }
+    }
+
+    let digest = digest(cmd, &sources)?;
+    match fs::read_to_string(cache_file(key)) {
+        Ok(prev) if prev.trim() == digest => Ok(Freshness::Fresh(
+            "sources unchanged since last successful run".to_string(),
+        )),
+        Ok(_) => Ok(Freshness::Stale(
+            "sources or command changed since last successful run".to_string(),
+        )),
+        Err(_) => Ok(Freshness::Stale(
+            "no previous successful run recorded".to_string(),
+        )),
+    }
+}
+
+/// Remember the current state of `node`'s sources after `cmd` ran successfully.
+pub fn record(node: &CmdNode, key: &str, cmd: &str) -> Result<(), YallaError> {
+    if node.sources.is_empty() {
+        return Ok(());
+    }
+    let sources = expand(&node.sources)?;
+    let digest = digest(cmd, &sources)?;
+    fs::create_dir_all(CACHE_DIR).map_err(io_error(format!("creating {CACHE_DIR}")))?;
+    let file = cache_file(key);
+    fs::write(&file, digest).map_err(io_error(format!("writing {}", file.display())))?;
+    Ok(())
+}
+
+fn io_error(context: String) -> impl FnOnce(std::io::Error) -> YallaError {
+    move |source| YallaError::Io { context, source }
+}
+
+fn cache_file(key: &str) -> PathBuf {
+    Path::new(CACHE_DIR).join(key)
+}
+
+/// Whether the glob `pattern` is valid but matches no files.
+pub fn matches_nothing(pattern: &str) -> bool {
+    glob::glob(pattern).is_ok_and(|mut paths| !paths.any(|p| p.is_ok_and(|p| p.is_file())))
+}
+
+fn expand(patterns: &[String]) -> Result<Vec<PathBuf>, YallaError> {
+    let mut files = Vec::new();
+    for pattern in patterns {
+        let paths = glob::glob(pattern).map_err(|e| YallaError::InvalidPattern {
+            pattern: pattern.clone(),
+            reason: e.msg.to_string(),
+        })?;
+        for p in paths {
+            let p = p.map_err(|e| YallaError::Io {
+                context: format!("reading {}", e.path().display()),
+                source: e.into_error(),
+            })?;
+            if p.is_file() {
+                files.push(p);
+            }
+        }
+    }
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+fn mtime(p: &Path) -> Result<SystemTime, YallaError> {
+    fs::metadata(p)
+        .and_then(|meta| meta.modified())
+        .map_err(io_error(format!("reading {}", p.display())))
+}
+
+fn newest(files: &[PathBuf]) -> Result<Option<SystemTime>, YallaError> {
+    let mut best = None;
+    for f in files {
+        let t = mtime(f)?;
+        best = Some(best.map_or(t, |b: SystemTime| b.max(t)));
+    }
+    Ok(best)
+}
+
+fn oldest(files: &[PathBuf]) -> Result<Option<SystemTime>, YallaError> {
+    let mut best = None;
+    for f in files {
+        let t = mtime(f)?;
+        best = Some(best.map_or(t, |b: SystemTime| b.min(t)));
+    }
+    Ok(best)
+}
+
+// FNV-1a over the command line, source paths and contents. Stable across
+// toolchains, unlike `DefaultHasher`.
+fn digest(cmd: &str, sources: &[PathBuf]) -> Result<String, YallaError> {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut feed = |bytes: &[u8]| {
+        for b in bytes {
+            h ^= u64::from(*b);
+            h = h.wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+    feed(cmd.as_bytes());
+    for p in sources {
+        let content = fs::read(p).map_err(io_error(format!("reading {}", p.display())))?;
+        feed(&[0]);
+        feed(p.to_string_lossy().as_bytes());
+        feed(&[0]);
+        feed(&content);
+    }
+    Ok(format!("{h:016x}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(sources: &[&str]) -> CmdNode {
+        CmdNode {
+            name: "build".to_string(),
+            cmd: Some("cargo build".to_string()),
+            sources: sources.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn force_and_missing_sources_always_run() {
+        let n = node(&["*.rs"]);
+        assert_eq!(
+            check(&n, "build", "cargo build", true).unwrap(),
+            Freshness::Stale("--force given".to_string())
+        );
+        assert_eq!(
+            check(&node(&[]), "build", "cargo build", false).unwrap(),
+            Freshness::Stale("no sources declared".to_string())
+        );
+    }
+
+    #[test]
+    fn sources_matching_nothing_always_run() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), "one").unwrap();
+        let base = dir.path().display();
+        let n = node(&[&format!("{base}/*.txt"), &format!("{base}/nothing/*.zz")]);
+        assert_eq!(
+            check(&n, "build", "cargo build", false).unwrap(),
+            Freshness::Stale(format!("source '{base}/nothing/*.zz' matches no files"))
+        );
+    }
+
+    #[test]
+    fn digest_tracks_content_and_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("a.txt");
+        fs::write(&src, "one").unwrap();
+
+        let first = digest("cargo build", std::slice::from_ref(&src)).unwrap();
+        assert_eq!(
+            first,
+            digest("cargo build", std::slice::from_ref(&src)).unwrap()
+        );
+
+        fs::write(&src, "two").unwrap();
+        let second = digest("cargo build", std::slice::from_ref(&src)).unwrap();
+        assert_ne!(first, second);
+        assert_ne!(second, digest("cargo build --release", &[src]).unwrap());
+    }
+}
@@ -0,0 +1,518 @@
+//! `yalla :check`: problems in the Yallafile, found without running anything
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::json;
+use toml::Table;
+
+use crate::cache;
+use crate::error::YallaError;
+use crate::model::{self, CmdNode};
+use crate::schema::{self, ROOT_KEYS, TASK_KEYS};
+use crate::toml::{ALIASES_TABLE, build_root, key_lines};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Problem {
+    pub severity: Severity,
+    /// Stable identifier of the check, e.g. `unknown-key`
+    pub rule: &'static str,
+    /// Dotted path of the task the problem belongs to; empty for the top level
+    pub task: String,
+    pub message: String,
+    pub line: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Human,
+    Json,
+    Sarif,
+}
+
+/// Every check, with a one-line description for SARIF consumers
+const RULES: &[(&str, &str)] = &[
+    ("load-error", "The Yallafile cannot be read or loaded"),
+    ("unknown-key", "A key yalla does not understand"),
+    (
+        "invalid-value",
+        "A known key with a value of the wrong type",
+    ),
+    (
+        "invalid-name",
+        "A task name that cannot be typed on the command line",
+    ),
+    ("empty-command", "A `cmd` with nothing to run"),
+    (
+        "unparseable-command",
+        "A `cmd` that cannot be split into words",
+    ),
+    (
+        "dangling-reference",
+        "An alias or see_also entry that points nowhere",
+    ),
+    (
+        "unmatched-source",
+        "A `sources` pattern that matches no files, so the task always runs",
+    ),
+];
+
+/// Check the Yallafile at `path`; problems are in file order.
+pub fn check(path: &str) -> Vec<Problem> {
+    let input = match fs::read_to_string(path) {
+        Ok(input) => input,
+        Err(e) => {
+            return vec![problem(
+                Severity::Error,
+                "load-error",
+                "",
+                format!("reading {path}: {e}"),
+                None,
+            )];
+        }
+    };
+    let table: Table = match toml::from_str(&input) {
+        Ok(t) => t,
+        Err(e) => {
+            let line = e.span().map(|s| input[..s.start].matches('\n').count() + 1);
+            let msg = e.message().trim_end().to_string();
+            return vec![problem(Severity::Error, "load-error", "", msg, line)];
+        }
+    };
+    let lines = key_lines(&input);
+
+    let mut problems = Vec::new();
+    check_table(&table, &mut Vec::new(), &lines, &mut problems);
+    // Values already reported as the wrong type would stop loading; leave them out
+    let mut table = table;
+    drop_invalid(&mut table, true);
+    match build_root("yalla", path, &input, &table) {
+        Ok(root) => {
+            // Patterns are relative to the directory yalla runs in, which is the Yallafile's
+            let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+            check_tree(&root, &root, dir, &mut Vec::new(), &lines, &mut problems)
+        }
+        Err(e) => {
+            let (message, line) = match &e {
+                YallaError::Parse(d) | YallaError::Invalid(d) => {
+                    (d.message.clone(), d.location.as_ref().map(|l| l.line))
+                }
+                e => (e.to_string(), None),
+            };
+            problems.push(problem(Severity::Error, "load-error", "", message, line));
+        }
+    }
+    problems.sort_by_key(|p| p.line.unwrap_or(0));
+    problems
+}
+
+fn problem(
+    severity: Severity,
+    rule: &'static str,
+    task: &str,
+    message: String,
+    line: Option<usize>,
+) -> Problem {
+    Problem {
+        severity,
+        rule,
+        task: task.to_string(),
+        message,
+        line,
+    }
+}
+
+// Keys and names, straight from the TOML
+fn check_table(
+    tbl: &Table,
+    path: &mut Vec<String>,
+    lines: &HashMap<Vec<String>, usize>,
+    out: &mut Vec<Problem>,
+) {
+    let task = path.join(".");
+    let top = path.is_empty();
+    for (k, v) in tbl {
+        path.push(k.clone());
+        let line = lines.get(path.as_slice()).copied();
+        if top && k == ALIASES_TABLE {
+            if !v.is_table() {
+                out.push(problem(
+                    Severity::Error,
+                    "invalid-value",
+                    "",
+                    format!("'{k}' must be a table of `name = \"dotted.path\"` entries"),
+                    line,
+                ));
+            }
+            // Aliases of aliases never resolve, and stop the file from loading
+            for (alias, target) in v.as_table().into_iter().flatten() {
+                if let Some(target) = target.as_str()
+                    && chains(v, target)
+                {
+                    out.push(problem(
+                        Severity::Error,
+                        "dangling-reference",
+                        alias,
+                        format!("alias points to '{target}', which is itself an alias"),
+                        lines.get(&vec![k.clone(), alias.clone()]).copied(),
+                    ));
+                }
+            }
+        } else if let Some(child) = schema::child_task(tbl, k, v) {
+            check_name(k, &path.join("."), line, out);
+            check_table(child, path, lines, out);
+        } else {
+            let known = TASK_KEYS
+                .iter()
+                .chain(if top { ROOT_KEYS } else { &[] })
+                .find(|key| key.name == k);
+            match known {
+                Some(key) if !key.kind.accepts(v) => out.push(problem(
+                    Severity::Error,
+                    "invalid-value",
+                    &task,
+                    format!(
+                        "'{k}' must be {}, found {}",
+                        key.kind.expected(),
+                        v.type_str()
+                    ),
+                    line,
+                )),
+                Some(_) => {}
+                None => out.push(problem(
+                    Severity::Error,
+                    "unknown-key",
+                    &task,
+                    format!("unknown key '{k}'"),
+                    line,
+                )),
+            }
+        }
+        path.pop();
+    }
+}
+
+// Remove what `check_table` reported as `invalid-value`
+fn drop_invalid(tbl: &mut Table, top: bool) {
+    let children: Vec<String> = tbl
+        .iter()
+        .filter(|(k, v)| schema::child_task(tbl, k, v).is_some())
+        .map(|(k, _)| k.clone())
+        .collect();
+    tbl.retain(|k, v| {
+        if top && k == ALIASES_TABLE {
+            let Some(aliases) = v.as_table() else {
+                return false;
+            };
+            let aliases = toml::Value::Table(aliases.clone());
+            if let Some(t) = v.as_table_mut() {
+                t.retain(|_, target| !target.as_str().is_some_and(|t| chains(&aliases, t)));
+            }
+            return true;
+        }
+        if children.iter().any(|c| c == k)
+            && let Some(child) = v.as_table_mut()
+        {
+            drop_invalid(child, false);
+            return true;
+        }
+        TASK_KEYS
+            .iter()
+            .chain(if top { ROOT_KEYS } else { &[] })
+            .find(|key| key.name == k)
+            .is_none_or(|key| key.kind.accepts(v))
+    });
+}
+
+// Whether `target` starts with the name of another entry of the `[aliases]` table
+fn chains(aliases: &toml::Value, target: &str) -> bool {
+    model::split_path(target)
+        .first()
+        .is_some_and(|first| aliases.get(first).is_some())
+}
+
+fn check_name(name: &str, task: &str, line: Option<usize>, out: &mut Vec<Problem>) {
+    let reason = if name.is_empty() {
+        Some("is empty")
+    } else if name.starts_with('-') {
+        Some("starts with '-' and would be read as an option")
+    } else if name.chars().any(char::is_whitespace) {
+        Some("contains whitespace")
+    } else {
+        None
+    };
+    if let Some(reason) = reason {
+        out.push(problem(
+            Severity::Error,
+            "invalid-name",
+            task,
+            format!("task name '{name}' {reason}"),
+            line,
+        ));
+    } else if name.contains('.') {
+        out.push(problem(
+            Severity::Warning,
+            "invalid-name",
+            task,
+            format!("task name '{name}' contains '.' and reads like a dotted path"),
+            line,
+        ));
+    }
+}
+
+// Commands and references, on the loaded tree
+fn check_tree(
+    root: &CmdNode,
+    node: &CmdNode,
+    dir: &Path,
+    path: &mut Vec<String>,
+    lines: &HashMap<Vec<String>, usize>,
+    out: &mut Vec<Problem>,
+) {
+    let task = path.join(".");
+    // Point at the key itself when the task defines it, else at the task
+    let node_line = node.source.as_ref().map(|s| s.line);
+    let line = |key: &str| {
+        let key_path = [path.as_slice(), &[key.to_string()]].concat();
+        lines.get(&key_path).copied().or(node_line)
+    };
+    if let Some(cmd) = &node.cmd {
+        match shlex::split(cmd) {
+            None => out.push(problem(
+                Severity::Error,
+                "unparseable-command",
+                &task,
+                format!("cmd `{cmd}` has unbalanced quotes or a trailing backslash"),
+                line("cmd"),
+            )),
+            Some(words) if words.is_empty() => out.push(problem(
+                Severity::Error,
+                "empty-command",
+                &task,
+                "cmd is empty".to_string(),
+                line("cmd"),
+            )),
+            Some(_) => {}
+        }
+    }
+    for pattern in &node.sources {
+        let in_dir = Path::new(&glob::Pattern::escape(&dir.to_string_lossy())).join(pattern);
+        if cache::matches_nothing(&in_dir.to_string_lossy()) {
+            out.push(problem(
+                Severity::Warning,
+                "unmatched-source",
+                &task,
+                format!("sources pattern '{pattern}' matches no files, so the task always runs"),
+                line("sources"),
+            ));
+        }
+    }
+    for entry in &node.see_also {
+        // Free text and URLs are fine; only things shaped like task paths are checked
+        let path_like = !entry.contains(char::is_whitespace) && !entry.contains(':');
+        if path_like && model::canonical_path(root, &model::split_path(entry)).is_none() {
+            out.push(problem(
+                Severity::Warning,
+                "dangling-reference",
+                &task,
+                format!("see_also entry '{entry}' is not a task"),
+                line("see_also"),
+            ));
+        }
+    }
+    for ch in &node.children {
+        path.push(ch.name.clone());
+        check_tree(root, ch, dir, path, lines, out);
+        path.pop();
+    }
+}
+
+pub fn render(problems: &[Problem], file: &str, format: Format) -> String {
+    match format {
+        Format::Human => {
+            let mut out = String::new();
+            for p in problems {
+                let location = match p.line {
+                    Some(line) => format!("{file}:{line}"),
+                    None => file.to_string(),
+                };
+                let severity = match p.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                };
+                let task = if p.task.is_empty() {
+                    String::new()
+                } else {
+                    format!("{}: ", p.task)
+                };
+                out.push_str(&format!(
+                    "{location}: {severity}: {task}{} [{}]\n",
+                    p.message, p.rule
+                ));
+            }
+            let errors = problems
+                .iter()
+                .filter(|p| p.severity == Severity::Error)
+                .count();
+            let warnings = problems.len() - errors;
+            out.push_str(&format!(
+                "{file}: {errors} error(s), {warnings} warning(s)\n"
+            ));
+            out
+        }
+        Format::Json => {
+            let mut json = serde_json::to_string_pretty(problems).expect("problems serialize");
+            json.push('\n');
+            json
+        }
+        Format::Sarif => {
+            let rules: Vec<_> = RULES
+                .iter()
+                .map(|(id, desc)| json!({ "id": id, "shortDescription": { "text": desc } }))
+                .collect();
+            let results: Vec<_> = problems
+                .iter()
+                .map(|p| {
+                    let mut location = json!({ "artifactLocation": { "uri": file } });
+                    if let Some(line) = p.line {
+                        location["region"] = json!({ "startLine": line });
+                    }
+                    json!({
+                        "ruleId": p.rule,
+                        "level": p.severity,
+                        "message": { "text": p.message },
+                        "locations": [{ "physicalLocation": location }]
+                    })
+                })
+                .collect();
+            let sarif = json!({
+                "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+                "version": "2.1.0",
+                "runs": [{
+                    "tool": { "driver": { "name": "yalla", "rules": rules } },
+                    "results": results
+                }]
+            });
+            let mut json = serde_json::to_string_pretty(&sarif).expect("sarif serializes");
+            json.push('\n');
+            json
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One `line severity rule task` string per problem, for compact assertions
+    fn check_str(input: &str) -> Vec<String> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Yallafile");
+        fs::write(&path, input).unwrap();
+        check(path.to_str().unwrap())
+            .into_iter()
+            .map(|p| {
+                let line = p.line.map_or("-".to_string(), |l| l.to_string());
+                format!("{line} {:?} {} {}", p.severity, p.rule, p.task)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reports_problems_with_lines() {
+        let problems = check_str(
+            "title = \"t\"\n\
+             [build]\n\
+             cmd = \"\"\n\
+             comand = \"cargo build\"\n\
+             [lint]\n\
+             cmd = \"echo 'unterminated\"\n\
+             on_unmet = \"ignore\"\n\
+             see_also = [\"nope\", \"https://example.com\"]\n\
+             [\"my task\"]\n\
+             cmd = \"true\"\n",
+        );
+        assert_eq!(
+            problems,
+            [
+                "3 Error empty-command build",
+                "4 Error unknown-key build",
+                "6 Error unparseable-command lint",
+                "7 Error invalid-value lint",
+                "8 Warning dangling-reference lint",
+                "9 Error invalid-name my task",
+            ]
+        );
+    }
+
+    #[test]
+    fn alias_of_alias_is_dangling() {
+        let problems =
+            check_str("[aliases]\nt = \"ci.test\"\nu = \"t\"\n[ci.test]\ncmd = \"true\"\n");
+        assert_eq!(problems, ["3 Error dangling-reference u"]);
+    }
+
+    #[test]
+    fn stdin_table_is_a_value_not_a_task() {
+        assert!(check_str("[seed]\ncmd = \"psql\"\nstdin = { file = \"seed.sql\" }\n").is_empty());
+        assert_eq!(
+            check_str("[seed]\ncmd = \"psql\"\nstdin = { path = \"seed.sql\" }\n"),
+            ["3 Error invalid-value seed"]
+        );
+    }
+
+    #[test]
+    fn stdin_table_of_a_namespace_is_a_task() {
+        // Only a command has a stdin, so here `stdin` is a task, and `file` is not a task key
+        assert_eq!(
+            check_str("[ci.stdin]\nfile = \"seed.sql\"\n"),
+            ["2 Error unknown-key ci.stdin"]
+        );
+        assert!(check_str("[ci.stdin]\ncmd = \"cat\"\n").is_empty());
+    }
+
+    #[test]
+    fn sources_matching_nothing_are_reported() {
+        assert_eq!(
+            check_str("[gen]\ncmd = \"gen\"\nsources = [\"Yallafile\", \"nothing/*.zz\"]\n"),
+            ["3 Warning unmatched-source gen"]
+        );
+    }
+
+    #[test]
+    fn load_errors_are_problems() {
+        assert_eq!(check_str("[build\ncmd = \"x\"\n"), ["1 Error load-error "]);
+    }
+
+    #[test]
+    fn sarif_lists_rules_and_results() {
+        let problems = [problem(
+            Severity::Warning,
+            "dangling-reference",
+            "lint",
+            "see_also entry 'x' is not a task".to_string(),
+            Some(3),
+        )];
+        let sarif: serde_json::Value =
+            serde_json::from_str(&render(&problems, "Yallafile", Format::Sarif)).unwrap();
+        let run = &sarif["runs"][0];
+        assert_eq!(sarif["version"], "2.1.0");
+        assert_eq!(
+            run["tool"]["driver"]["rules"].as_array().unwrap().len(),
+            RULES.len()
+        );
+        assert_eq!(run["results"][0]["level"], "warning");
+        assert_eq!(
+            run["results"][0]["locations"][0]["physicalLocation"]["region"]["startLine"],
+            3
+        );
+    }
+}
@@ -1,5 +1,64 @@
-use anyhow::anyhow;
-use clap::Command;
+use clap::{Arg, ArgAction, Command};
+
+use crate::error::YallaError;
+
+/// Add yalla's own options to the root command, ahead of any task path.
+pub fn with_runner_flags(cmd: Command) -> Command {
+    cmd.arg(
+        Arg::new("force")
+            .long("force")
+            .action(ArgAction::SetTrue)
+            .help("Run the task even if its sources are unchanged"),
+    )
+    .arg(
+        Arg::new("watch")
+            .long("watch")
+            .action(ArgAction::SetTrue)
+            .help("Rerun the task whenever its files change"),
+    )
+    .arg(
+        Arg::new("yes")
+            .short('y')
+            .long("yes")
+            .action(ArgAction::SetTrue)
+            .help("Run tasks that ask for confirmation without prompting"),
+    )
+    .arg(
+        Arg::new("verbose")
+            .short('v')
+            .long("verbose")
+            .action(ArgAction::SetTrue)
+            .help("Explain why a task ran or was skipped"),
+    )
+    .arg(
+        Arg::new("time")
+            .long("time")
+            .action(ArgAction::SetTrue)
+            .help("Print how long each task took when the run finishes"),
+    )
+    .arg(
+        Arg::new("report")
+            .long("report")
+            .value_name("FILE")
+            .value_parser(clap::value_parser!(std::path::PathBuf))
+            .help("Write the outcome of each task to FILE as JUnit XML"),
+    )
+    .arg(
+        Arg::new("log_dir")
+            .long("log-dir")
+            .value_name("DIR")
+            .value_parser(clap::value_parser!(std::path::PathBuf))
+            .help("Also write each task's output to a log file in DIR"),
+    )
+    .arg(
+        Arg::new("output")
+            .long("output")
+            .value_name("MODE")
+            .value_parser(["raw", "prefixed", "grouped"])
+            .default_value("raw")
+            .help("How to show task output: as is, [task]-prefixed, or as one CI log group"),
+    )
+}
 
 pub fn subcommand_path(m: &clap::ArgMatches) -> Vec<String> {
     let mut p = Vec::new();
@@ -10,12 +69,26 @@ pub fn subcommand_path(m: &clap::ArgMatches) -> Vec<String> {
     }
     p
 }
+/// Arguments given after the task path, to be passed on to its command.
+pub fn trailing_args(m: &clap::ArgMatches) -> Vec<String> {
+    let mut cur = m;
+    while let Some((_, sub)) = cur.subcommand() {
+        cur = sub;
+    }
+    match cur.try_get_many::<String>("args") {
+        Ok(Some(args)) => args.cloned().collect(),
+        _ => Vec::new(),
+    }
+}
+
 pub fn print_help(cmd: &mut Command, path: &[String]) -> anyhow::Result<()> {
     let mut cur = cmd;
     for seg in path {
         cur = cur
             .find_subcommand_mut(seg)
-            .ok_or_else(|| anyhow!("No such subcommand: {}", seg))?;
+            .ok_or_else(|| YallaError::UnknownTask {
+                path: path.join("."),
+            })?;
     }
     cur.print_help()?;
     println!();
@@ -0,0 +1,120 @@
+//! The `yalla` command line: loads the Yallafile, builds the clap tree and dispatches
+use std::process::ExitCode;
+
+use anyhow::Result;
+use clap_complete::CompleteEnv;
+
+use crate::builtin;
+use crate::clap_util::{print_help, subcommand_path, trailing_args, with_runner_flags};
+use crate::error::{EX_USAGE, YallaError};
+use crate::model::{self, CmdNode, build_clap_from_root};
+use crate::resolve;
+use crate::run;
+use crate::toml::{YALLAFILE, load_root};
+
+/// Run the command line and turn the outcome into an exit code (see [`YallaError::exit_code`]).
+pub fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) if e.is::<Reported>() => ExitCode::from(EX_USAGE as u8),
+        Err(e) => {
+            // Same report as returning the error from `main`
+            eprintln!("Error: {e:?}");
+            let code = e
+                .chain()
+                .find_map(|c| c.downcast_ref::<YallaError>())
+                .map_or(1, YallaError::exit_code);
+            ExitCode::from(u8::try_from(code).unwrap_or(1))
+        }
+    }
+}
+
+// A command line clap rejected; clap has already printed why
+#[derive(Debug)]
+struct Reported;
+
+impl std::fmt::Display for Reported {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("invalid command line")
+    }
+}
+
+impl std::error::Error for Reported {}
+
+fn run() -> Result<()> {
+    let empty = || CmdNode {
+        name: "yalla".to_string(),
+        ..Default::default()
+    };
+    let root = match load_root("yalla", YALLAFILE) {
+        Ok(root) => root,
+        // `:check` reports what is wrong with the file itself
+        Err(_) if std::env::args().any(|a| a == ":check") => empty(),
+        Err(YallaError::NotFound { .. }) => {
+            // Built-ins such as `:schema` still work; anything else is a no-op
+            if std::env::var_os("COMPLETE").is_none()
+                && !std::env::args().skip(1).any(|a| builtin::is_builtin(&a))
+            {
+                return Ok(());
+            }
+            empty()
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let mut clap_root = builtin::with_builtins(with_runner_flags(build_clap_from_root(&root)));
+
+    // Shell completion callbacks (`COMPLETE=<shell> yalla -- ...`) answer and exit here
+    CompleteEnv::with_factory(|| clap_root.clone()).complete();
+
+    // Parse CLI
+    let args: Vec<String> = std::env::args().collect();
+    let expanded = resolve::expand_args(&root, &clap_root, &args[1..])?;
+    let matches = match clap_root
+        .clone()
+        .try_get_matches_from(args[..1].iter().chain(&expanded))
+    {
+        Ok(matches) => matches,
+        // `--help` and `--version` end up here too, but are not errors
+        Err(e) => {
+            e.print()?;
+            return match e.use_stderr() {
+                true => Err(Reported.into()),
+                false => Ok(()),
+            };
+        }
+    };
+    let mut opts = run::Options::from_matches(&matches);
+    // A top-level `time = true` makes `--time` the default
+    opts.time |= root.time;
+    if let Some((name, sub)) = matches.subcommand()
+        && builtin::is_builtin(name)
+    {
+        return builtin::run(&root, name, sub, opts);
+    }
+
+    let path = subcommand_path(&matches);
+    // Aliases resolve to the task's real path so caching etc. see one name
+    let path = model::canonical_path(&root, &path).unwrap_or(path);
+
+    if path.is_empty() {
+        // Root requested: show top-level help
+        print_help(&mut clap_root, &[])?;
+        return Ok(());
+    }
+
+    run::refuse_private(&root, &path)?;
+
+    match model::find_node(&root, &path) {
+        Some(node) if node.cmd.is_some() => {
+            Ok(run::task(node, &path, &trailing_args(&matches), opts)?)
+        }
+        Some(_) => {
+            // Namespace-only: show contextual help
+            print_help(&mut clap_root, &path)
+        }
+        None => {
+            // Shouldn't happen (clap validated), but show the closest help just in case
+            print_help(&mut clap_root, &[])
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! Errors that point into the Yallafile, rendered with the offending line and a caret
+//!
+//! Problems found while building the task tree only know the key they are about;
+//! `load_root` then locates that key in the file so the error can show where it is.
+use std::fmt;
+use std::ops::Range;
+
+/// A problem in a Yallafile; `Display` shows the offending line once located
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Diagnostic {
+    pub message: String,
+    pub hint: Option<String>,
+    /// Key path the problem is about (e.g. `["tools", "lint", "cmd"]`); empty for the whole file
+    pub key: Vec<String>,
+    /// Set by `locate`: the file and the byte range the problem covers
+    pub location: Option<Box<Snippet>>,
+}
+
+/// Where in the file a [`Diagnostic`] points
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Snippet {
+    pub file: String,
+    pub span: Range<usize>,
+    /// 1-based, as shown to users
+    pub line: usize,
+    pub column: usize,
+    /// The full source line containing the start of `span`
+    pub source_line: String,
+}
+
+impl Diagnostic {
+    pub fn new(message: impl Into<String>) -> Self {
+        Diagnostic {
+            message: message.into(),
+            hint: None,
+            key: Vec::new(),
+            location: None,
+        }
+    }
+
+    pub fn at_key(mut self, key: Vec<String>) -> Self {
+        self.key = key;
+        self
+    }
+
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    /// Point the diagnostic at `span` of `input`, the contents of `file`.
+    pub fn locate(mut self, file: &str, input: &str, span: Range<usize>) -> Self {
+        let start = span.start.min(input.len());
+        let line_start = input[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = input[start..].find('\n').map_or(input.len(), |i| start + i);
+        self.location = Some(Box::new(Snippet {
+            file: file.to_string(),
+            line: input[..start].matches('\n').count() + 1,
+            column: input[line_start..start].chars().count() + 1,
+            source_line: input[line_start..line_end]
+                .trim_end_matches('\r')
+                .to_string(),
+            span: start..span.end.max(start),
+        }));
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(loc) = &self.location else {
+            return write!(f, "{}", self.message);
+        };
+        writeln!(
+            f,
+            "{}:{}:{}: {}",
+            loc.file, loc.line, loc.column, self.message
+        )?;
+
+        // The caret covers the span, but never runs past the end of the first line
+        let gutter = " ".repeat(loc.line.to_string().len());
+        let width = loc.source_line.chars().count() + 1 - loc.column;
+        let carets = (loc.span.len()).clamp(1, width.max(1));
+        writeln!(f, "{gutter} |")?;
+        writeln!(f, "{} | {}", loc.line, loc.source_line)?;
+        write!(
+            f,
+            "{gutter} | {}{}",
+            " ".repeat(loc.column - 1),
+            "^".repeat(carets)
+        )?;
+        if let Some(hint) = &self.hint {
+            write!(f, "\n{gutter} = hint: {hint}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_line_column_caret_and_hint() {
+        let input = "[build]\ncmd = 42\n";
+        let d = Diagnostic::new("`cmd` must be a string, found integer")
+            .with_hint("quote the command: cmd = \"...\"")
+            .locate("Yallafile", input, 14..16);
+        assert_eq!(
+            d.to_string(),
+            "Yallafile:2:7: `cmd` must be a string, found integer\n  \
+             |\n\
+             2 | cmd = 42\n  \
+             |       ^^\n  \
+             = hint: quote the command: cmd = \"...\""
+        );
+    }
+
+    #[test]
+    fn unlocated_shows_message_only() {
+        let d = Diagnostic::new("Alias 't' points to unknown task 'x'").with_hint("h");
+        assert_eq!(d.to_string(), "Alias 't' points to unknown task 'x'");
+    }
+}
@@ -0,0 +1,272 @@
+//! Markdown and man page documentation rendered from the task tree
+use crate::list;
+use crate::model::{self, CmdNode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Markdown,
+    Man,
+}
+
+impl Format {
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Markdown => "markdown",
+            Format::Man => "man",
+        }
+    }
+}
+
+pub fn render(root: &CmdNode, format: Format) -> String {
+    match format {
+        Format::Markdown => markdown(root),
+        Format::Man => man(root),
+    }
+}
+
+/// A documented task: its command line, and the node it stands for
+struct Section<'a> {
+    invocation: String,
+    node: &'a CmdNode,
+}
+
+impl Section<'_> {
+    fn usage(&self) -> String {
+        let n = self.node;
+        let mut usage = self.invocation.clone();
+        if !n.children.is_empty() {
+            usage.push_str(if n.cmd.is_some() {
+                " [TASK]"
+            } else {
+                " <TASK>"
+            });
+        }
+        if n.cmd.is_some() || n.alias_of.is_some() {
+            usage.push_str(" [ARGS]...");
+        }
+        usage
+    }
+
+    fn subtasks(&self) -> Vec<&str> {
+        list::visible(self.node, false)
+            .into_iter()
+            .map(|c| c.name.as_str())
+            .collect()
+    }
+
+    // Everything below the description, as (label, value) pairs
+    fn facts(&self, root: &CmdNode) -> Vec<(&'static str, String)> {
+        let n = self.node;
+        let mut facts = Vec::new();
+        if !n.aliases.is_empty() {
+            facts.push(("Aliases", n.aliases.join(", ")));
+        }
+        if !n.choices.is_empty() {
+            facts.push(("Arguments", format!("one of {}", n.choices.join(", "))));
+        }
+        let mut requires: Vec<String> = n.requires.clone();
+        requires.extend(n.requires_env.iter().map(|v| format!("${v}")));
+        if !requires.is_empty() {
+            facts.push(("Requires", requires.join(", ")));
+        }
+        if !n.platforms.is_empty() {
+            facts.push(("Platforms", n.platforms.join(", ")));
+        }
+        if let Some(cond) = &n.condition {
+            facts.push(("Runs if", cond.clone()));
+        }
+        if let Some(question) = &n.confirm {
+            facts.push(("Asks", question.clone()));
+        }
+        if !n.see_also.is_empty() {
+            let refs: Vec<String> = n
+                .see_also
+                .iter()
+                .map(|r| model::see_also_ref(root, r))
+                .collect();
+            facts.push(("See also", refs.join(", ")));
+        }
+        facts
+    }
+}
+
+// Every visible node, depth first in display order. Hidden and private tasks
+// are left out, like in help.
+fn sections(root: &CmdNode) -> Vec<Section<'_>> {
+    fn walk<'a>(n: &'a CmdNode, invocation: &str, out: &mut Vec<Section<'a>>) {
+        for ch in list::visible(n, false) {
+            let invocation = format!("{invocation} {}", ch.name);
+            out.push(Section {
+                invocation: invocation.clone(),
+                node: ch,
+            });
+            walk(ch, &invocation, out);
+        }
+    }
+    let mut out = Vec::new();
+    walk(root, "yalla", &mut out);
+    out
+}
+
+fn markdown(root: &CmdNode) -> String {
+    let mut out = String::from("# Tasks\n\n");
+    if let Some(d) = &root.description {
+        out.push_str(&format!("{d}\n\n"));
+    }
+    out.push_str("<!-- Generated by `yalla :docs`; do not edit. -->\n");
+
+    for s in sections(root) {
+        let n = s.node;
+        out.push_str(&format!("\n## `{}`\n\n", s.invocation));
+        for d in [&n.description, &n.long_description].into_iter().flatten() {
+            out.push_str(&format!("{d}\n\n"));
+        }
+        out.push_str(&format!("Usage: `{}`\n", s.usage()));
+        if let Some(cmd) = &n.cmd {
+            out.push_str(&format!("\n```sh\n{cmd}\n```\n"));
+        }
+        if !n.examples.is_empty() {
+            out.push_str("\nExamples:\n\n");
+            for ex in &n.examples {
+                match &ex.desc {
+                    Some(desc) => out.push_str(&format!("- `{}`: {desc}\n", ex.cmd)),
+                    None => out.push_str(&format!("- `{}`\n", ex.cmd)),
+                }
+            }
+        }
+        let mut facts = s.facts(root);
+        let subtasks = s.subtasks();
+        if !subtasks.is_empty() {
+            facts.push(("Tasks", subtasks.join(", ")));
+        }
+        if !facts.is_empty() {
+            out.push('\n');
+            for (label, value) in facts {
+                out.push_str(&format!("- {label}: {value}\n"));
+            }
+        }
+    }
+    out
+}
+
+fn man(root: &CmdNode) -> String {
+    let mut out = String::from(".\\\" Generated by `yalla :docs --format man`; do not edit.\n");
+    out.push_str(".TH YALLA-TASKS 7\n");
+    out.push_str(".SH NAME\nyalla\\-tasks \\- tasks defined in the Yallafile\n");
+    out.push_str(".SH SYNOPSIS\n.B yalla\n.I task\n.RI [ args ...]\n");
+    if let Some(d) = &root.description {
+        out.push_str(&format!(".SH DESCRIPTION\n{}\n", roff(d)));
+    }
+    out.push_str(".SH TASKS\n");
+    for s in sections(root) {
+        let n = s.node;
+        out.push_str(&format!(".TP\n.B {}\n", roff(&s.usage())));
+        if let Some(d) = &n.description {
+            out.push_str(&format!("{}\n", roff(d)));
+        }
+        if let Some(long) = &n.long_description {
+            for para in long.split("\n\n") {
+                out.push_str(&format!(".IP\n{}\n", roff(para)));
+            }
+        }
+        if let Some(cmd) = &n.cmd {
+            out.push_str(&format!(".br\nRuns: \\f(CW{}\\fR\n", escape(cmd)));
+        }
+        for ex in &n.examples {
+            out.push_str(&format!(".br\nExample: \\f(CW{}\\fR\n", escape(&ex.cmd)));
+            if let Some(desc) = &ex.desc {
+                out.push_str(&format!("\\(em {}\n", escape(desc)));
+            }
+        }
+        let mut facts = s.facts(root);
+        let subtasks = s.subtasks();
+        if !subtasks.is_empty() {
+            facts.push(("Tasks", subtasks.join(", ")));
+        }
+        for (label, value) in facts {
+            out.push_str(&format!(".br\n{label}: {}\n", roff(&value)));
+        }
+    }
+    out
+}
+
+// Escape text for use inside a roff line
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\e").replace('-', "\\-")
+}
+
+// Escape text made of whole roff lines, where a leading `.` or `'` begins a request
+fn roff(s: &str) -> String {
+    let lines: Vec<String> = escape(s)
+        .lines()
+        .map(|l| {
+            if l.starts_with(['.', '\'']) {
+                format!("\\&{l}")
+            } else {
+                l.to_string()
+            }
+        })
+        .collect();
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root() -> CmdNode {
+        CmdNode {
+            name: "yalla".to_string(),
+            description: Some("Demo".to_string()),
+            children: vec![
+                CmdNode {
+                    name: "deploy".to_string(),
+                    description: Some("Ship it".to_string()),
+                    cmd: Some("./deploy.sh --env".to_string()),
+                    choices: vec!["dev".to_string(), "prod".to_string()],
+                    ..Default::default()
+                },
+                CmdNode {
+                    name: "db".to_string(),
+                    children: vec![
+                        CmdNode {
+                            name: "migrate".to_string(),
+                            cmd: Some("diesel migration run".to_string()),
+                            ..Default::default()
+                        },
+                        CmdNode {
+                            name: "reset".to_string(),
+                            cmd: Some("diesel database reset".to_string()),
+                            private: true,
+                            ..Default::default()
+                        },
+                    ],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn markdown_documents_visible_tasks() {
+        let md = render(&root(), Format::Markdown);
+        assert!(md.starts_with("# Tasks\n\nDemo\n"));
+        assert!(md.contains("## `yalla db`\n\nUsage: `yalla db <TASK>`\n\n- Tasks: migrate\n"));
+        assert!(md.contains(
+            "## `yalla deploy`\n\nShip it\n\nUsage: `yalla deploy [ARGS]...`\n\n\
+             ```sh\n./deploy.sh --env\n```\n\n- Arguments: one of dev, prod\n"
+        ));
+        assert!(!md.contains("reset"));
+    }
+
+    #[test]
+    fn man_escapes_roff() {
+        let man = render(&root(), Format::Man);
+        assert!(man.contains(".TH YALLA-TASKS 7\n"));
+        assert!(man.contains(
+            ".B yalla deploy [ARGS]...\nShip it\n.br\nRuns: \\f(CW./deploy.sh \\-\\-env\\fR\n"
+        ));
+        assert_eq!(roff(".hidden"), "\\&.hidden");
+    }
+}
@@ -0,0 +1,281 @@
+//! Errors returned by the library API, and the exit codes the CLI maps them to
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+use crate::diagnostic::Diagnostic;
+use crate::guard::Requirement;
+
+// yalla's own exit codes, from BSD's sysexits.h, so that they rarely clash with a task's
+pub(crate) const EX_USAGE: i32 = 64;
+pub(crate) const EX_UNAVAILABLE: i32 = 69;
+pub(crate) const EX_IOERR: i32 = 74;
+pub(crate) const EX_CONFIG: i32 = 78;
+
+/// What went wrong loading a Yallafile or running one of its tasks
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum YallaError {
+    /// There is no Yallafile at `path`
+    NotFound { path: String },
+    /// The Yallafile exists but cannot be read
+    Read { path: String, source: io::Error },
+    /// The Yallafile is not valid TOML
+    Parse(Diagnostic),
+    /// Valid TOML that does not describe a task tree, e.g. a value of the wrong type
+    Invalid(Diagnostic),
+    /// A task's `cmd` (with its arguments) cannot be turned into a program and arguments
+    InvalidCommand { cmd: String, reason: String },
+    /// A `sources`, `outputs` or `watch` entry is not a valid glob pattern
+    InvalidPattern { pattern: String, reason: String },
+    /// No task or namespace at this dotted path
+    UnknownTask { path: String },
+    /// A prefix on the command line matches several tasks: their commands and descriptions
+    Ambiguous {
+        prefix: String,
+        candidates: Vec<(String, Option<String>)>,
+    },
+    /// The path names a namespace, which has no command to run
+    NotRunnable { path: String },
+    /// The task is `private`: only another task's command can run it
+    Private { path: String },
+    /// `--watch` was combined with an option it does not support, e.g. a log file
+    WatchConflict { task: String, option: String },
+    /// The task's guards are not satisfied and it sets `on_unmet = "fail"`; `unmet` holds
+    /// the requirements that do not hold
+    Unmet {
+        task: String,
+        unmet: Vec<Requirement>,
+    },
+    /// The task asks for confirmation, but there is no terminal to ask on
+    NeedsConfirmation { task: String },
+    /// The confirmation prompt was answered no
+    Cancelled { task: String },
+    /// The task's program could not be started; when it was not found, `suggestions`
+    /// holds similarly named programs on PATH and `install_hint` the task's `install_hint`
+    Spawn {
+        task: String,
+        program: String,
+        source: io::Error,
+        suggestions: Vec<String>,
+        install_hint: Option<String>,
+    },
+    /// The task ran and exited with a nonzero `code`
+    Exit { task: String, code: i32 },
+    /// The task was killed by `signal` (Unix only)
+    Signal { task: String, signal: i32 },
+    /// The task ran longer than its `timeout` and was killed
+    Timeout { task: String, after: Duration },
+    /// A file around the task could not be read or written, e.g. one of its `sources`, its
+    /// `log` or the `--report`; `context` says which and what was being done with it
+    Io { context: String, source: io::Error },
+    /// Watch mode could not keep track of file changes
+    Watch { reason: String },
+}
+
+impl YallaError {
+    /// The process exit code the `yalla` binary uses for this error:
+    ///
+    /// | Code    | Meaning                                                         |
+    /// |---------|-----------------------------------------------------------------|
+    /// | 1       | Any other failure                                               |
+    /// | 64      | Bad command line, e.g. an unknown task or conflicting options   |
+    /// | 69      | The task was not run: unmet guards or no confirmation           |
+    /// | 74      | A file the run needs could not be read, written or watched      |
+    /// | 78      | The Yallafile cannot be read or is invalid                      |
+    /// | 124     | The task ran past its `timeout` and was killed                  |
+    /// | 126     | The task's program exists but could not be started              |
+    /// | 127     | The task's program was not found                                |
+    /// | 128 + N | The task was killed by signal N                                 |
+    /// | other   | The task's own nonzero exit code, passed through                |
+    ///
+    /// yalla's own codes are those of BSD's `sysexits.h`, which few programs use.
+    /// [`NotFound`](YallaError::NotFound) maps to 78 as well, but the binary never exits with
+    /// it: without a Yallafile, `yalla` has nothing to do and exits 0.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            YallaError::UnknownTask { .. }
+            | YallaError::Ambiguous { .. }
+            | YallaError::NotRunnable { .. }
+            | YallaError::Private { .. }
+            | YallaError::WatchConflict { .. } => EX_USAGE,
+            YallaError::NotFound { .. }
+            | YallaError::Read { .. }
+            | YallaError::Parse(_)
+            | YallaError::Invalid(_)
+            | YallaError::InvalidCommand { .. }
+            | YallaError::InvalidPattern { .. } => EX_CONFIG,
+            YallaError::Unmet { .. }
+            | YallaError::NeedsConfirmation { .. }
+            | YallaError::Cancelled { .. } => EX_UNAVAILABLE,
+            YallaError::Spawn { source, .. } if source.kind() == io::ErrorKind::NotFound => 127,
+            YallaError::Spawn { .. } => 126,
+            YallaError::Exit { code, .. } => *code,
+            YallaError::Signal { signal, .. } => 128 + signal,
+            // As GNU `timeout` exits
+            YallaError::Timeout { .. } => 124,
+            YallaError::Io { .. } | YallaError::Watch { .. } => EX_IOERR,
+        }
+    }
+}
+
+impl fmt::Display for YallaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            YallaError::NotFound { path } => write!(f, "{path} not found"),
+            YallaError::Read { path, .. } => write!(f, "reading {path}"),
+            YallaError::Parse(d) | YallaError::Invalid(d) => write!(f, "{d}"),
+            YallaError::InvalidCommand { cmd, reason } => write!(f, "Cannot run `{cmd}`: {reason}"),
+            YallaError::InvalidPattern { pattern, reason } => {
+                write!(f, "Invalid glob pattern '{pattern}': {reason}")
+            }
+            YallaError::UnknownTask { path } => write!(f, "No such task: {path}"),
+            YallaError::Ambiguous { prefix, candidates } => {
+                write!(f, "'{prefix}' is ambiguous; it could be:")?;
+                let width = candidates.iter().map(|(c, _)| c.len()).max().unwrap_or(0);
+                for (cmd, desc) in candidates {
+                    match desc {
+                        Some(d) => write!(f, "\n  {cmd:width$}  {d}")?,
+                        None => write!(f, "\n  {cmd}")?,
+                    }
+                }
+                Ok(())
+            }
+            YallaError::NotRunnable { path } => write!(f, "{path} is a namespace, not a task"),
+            YallaError::Private { path } => write!(
+                f,
+                "Task '{path}' is private; only other tasks can run it, e.g. from their `cmd`"
+            ),
+            YallaError::WatchConflict { task, option } => write!(
+                f,
+                "Cannot watch {task} with {option}: watched tasks write straight to the terminal"
+            ),
+            YallaError::Unmet { task, unmet } => {
+                write!(f, "Requirements for {task} not met")?;
+                for u in unmet {
+                    write!(f, "\n  {u}")?;
+                }
+                Ok(())
+            }
+            YallaError::NeedsConfirmation { task } => write!(
+                f,
+                "{task} asks for confirmation; pass --yes to run it non-interactively"
+            ),
+            YallaError::Cancelled { task } => write!(f, "{task} cancelled"),
+            YallaError::Spawn {
+                task,
+                program,
+                source,
+                suggestions,
+                install_hint,
+            } => {
+                if source.kind() != io::ErrorKind::NotFound {
+                    return write!(f, "task '{task}': cannot start '{program}'");
+                }
+                let where_ = if program.contains('/') {
+                    ""
+                } else {
+                    " on PATH"
+                };
+                write!(f, "task '{task}': program '{program}' not found{where_}")?;
+                match suggestions.as_slice() {
+                    [] => {}
+                    [one] => write!(f, "\n  did you mean '{one}'?")?,
+                    many => write!(f, "\n  similar programs: {}", many.join(", "))?,
+                }
+                if let Some(hint) = install_hint {
+                    write!(f, "\n  to install it: {hint}")?;
+                }
+                Ok(())
+            }
+            YallaError::Exit { task, code } => write!(f, "{task} failed with exit code {code}"),
+            YallaError::Signal { task, signal } => {
+                write!(f, "{task} was killed by signal {signal}")
+            }
+            YallaError::Timeout { task, after } => {
+                write!(f, "{task} timed out after {}s", after.as_secs())
+            }
+            YallaError::Io { context, .. } => write!(f, "{context}"),
+            YallaError::Watch { reason } => write!(f, "Cannot watch for changes: {reason}"),
+        }
+    }
+}
+
+impl Error for YallaError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            YallaError::Read { source, .. } | YallaError::Io { source, .. } => Some(source),
+            // "not found on PATH" already says it all
+            YallaError::Spawn { source, .. } if source.kind() != io::ErrorKind::NotFound => {
+                Some(source)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_codes_tell_configuration_from_task_failures() {
+        let task = || "ci.test".to_string();
+        let spawn = |kind| YallaError::Spawn {
+            task: task(),
+            program: "cargo".to_string(),
+            source: io::Error::from(kind),
+            suggestions: Vec::new(),
+            install_hint: None,
+        };
+        let invalid = YallaError::Invalid(Diagnostic::new("`cmd` must be a string"));
+        assert_eq!(invalid.exit_code(), 78);
+        assert_eq!(YallaError::Private { path: task() }.exit_code(), 64);
+        let unmet = YallaError::Unmet {
+            task: task(),
+            unmet: vec![Requirement::Env("DATABASE_URL".to_string())],
+        };
+        assert_eq!(unmet.exit_code(), 69);
+        assert_eq!(
+            unmet.to_string(),
+            "Requirements for ci.test not met\n  required environment variable 'DATABASE_URL' is not set"
+        );
+        assert_eq!(spawn(io::ErrorKind::NotFound).exit_code(), 127);
+        assert_eq!(spawn(io::ErrorKind::PermissionDenied).exit_code(), 126);
+        let exit = YallaError::Exit {
+            task: task(),
+            code: 42,
+        };
+        assert_eq!(exit.exit_code(), 42);
+        let signal = YallaError::Signal {
+            task: task(),
+            signal: 9,
+        };
+        assert_eq!(signal.exit_code(), 137);
+        let timeout = YallaError::Timeout {
+            task: task(),
+            after: Duration::from_secs(30),
+        };
+        assert_eq!(timeout.exit_code(), 124);
+        assert_eq!(timeout.to_string(), "ci.test timed out after 30s");
+    }
+
+    #[test]
+    fn missing_program_suggests_alternatives_and_install_hint() {
+        let err = YallaError::Spawn {
+            task: "db.migrate".to_string(),
+            program: "diesel".to_string(),
+            source: io::Error::from(io::ErrorKind::NotFound),
+            suggestions: vec!["diesel2".to_string()],
+            install_hint: Some("cargo install diesel_cli".to_string()),
+        };
+        assert_eq!(
+            err.to_string(),
+            "task 'db.migrate': program 'diesel' not found on PATH\n  \
+             did you mean 'diesel2'?\n  \
+             to install it: cargo install diesel_cli"
+        );
+        assert!(err.source().is_none());
+    }
+}
@@ -1,6 +0,0 @@
-use anyhow::Error;
-
-pub fn not_found(e: &Error) -> bool {
-    e.downcast_ref::<std::io::Error>()
-        .is_some_and(|io| io.kind() == std::io::ErrorKind::NotFound)
-}
@@ -0,0 +1,124 @@
+//! Preconditions (`requires`, `requires_env`, `platforms`, `if`) checked before a task runs
+use std::env::consts;
+use std::fmt;
+
+use crate::model::CmdNode;
+use crate::process;
+
+/// A precondition of a task that does not hold
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Requirement {
+    /// A program from `requires` is not on PATH
+    Program(String),
+    /// A variable from `requires_env` is unset or empty
+    Env(String),
+    /// The task's `platforms`, none of which is this one
+    Platform(Vec<String>),
+    /// The `if` command, which failed
+    Condition(String),
+}
+
+impl Requirement {
+    /// Short form used to mark tasks in help output
+    pub(crate) fn label(&self) -> String {
+        match self {
+            Requirement::Program(p) => p.clone(),
+            Requirement::Env(v) => format!("${v}"),
+            Requirement::Platform(_) => consts::OS.to_string(),
+            Requirement::Condition(c) => format!("if `{c}`"),
+        }
+    }
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Requirement::Program(p) => write!(f, "required program '{p}' not found on PATH"),
+            Requirement::Env(v) => write!(f, "required environment variable '{v}' is not set"),
+            Requirement::Platform(ps) => {
+                write!(f, "runs only on {}, this is {}", ps.join(", "), consts::OS)
+            }
+            Requirement::Condition(c) => write!(f, "condition `{c}` failed"),
+        }
+    }
+}
+
+/// Checks that are cheap and side-effect free, so help output can use them.
+pub fn static_unmet(node: &CmdNode) -> Vec<Requirement> {
+    let mut unmet = Vec::new();
+    if !node.platforms.is_empty()
+        && !node
+            .platforms
+            .iter()
+            .any(|p| p == consts::OS || p == consts::FAMILY)
+    {
+        unmet.push(Requirement::Platform(node.platforms.clone()));
+    }
+    for p in &node.requires {
+        if process::find_program(p).is_none() {
+            unmet.push(Requirement::Program(p.clone()));
+        }
+    }
+    for v in &node.requires_env {
+        if std::env::var_os(v).is_none_or(|val| val.is_empty()) {
+            unmet.push(Requirement::Env(v.clone()));
+        }
+    }
+    unmet
+}
+
+/// All guards, including running the `if` condition.
+pub fn unmet(node: &CmdNode) -> Vec<Requirement> {
+    let mut unmet = static_unmet(node);
+    // Only evaluate the condition once the cheap checks pass
+    if unmet.is_empty()
+        && let Some(cond) = &node.condition
+        && !process::succeeds(cond)
+    {
+        unmet.push(Requirement::Condition(cond.clone()));
+    }
+    unmet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_each_unmet_requirement() {
+        let node = CmdNode {
+            name: "deploy".to_string(),
+            requires: vec!["sh".to_string(), "yalla-no-such-program".to_string()],
+            requires_env: vec!["YALLA_GUARD_TEST_UNSET".to_string()],
+            platforms: vec!["plan9".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            static_unmet(&node),
+            vec![
+                Requirement::Platform(vec!["plan9".to_string()]),
+                Requirement::Program("yalla-no-such-program".to_string()),
+                Requirement::Env("YALLA_GUARD_TEST_UNSET".to_string()),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn condition_runs_only_when_static_checks_pass() {
+        let mut node = CmdNode {
+            name: "guarded".to_string(),
+            condition: Some("false".to_string()),
+            platforms: vec![consts::FAMILY.to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            unmet(&node),
+            vec![Requirement::Condition("false".to_string())]
+        );
+
+        node.condition = Some("true".to_string());
+        assert!(unmet(&node).is_empty());
+    }
+}
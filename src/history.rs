@@ -0,0 +1,220 @@
+//! A log of executed tasks in `.yalla/history.jsonl`, shown and replayed by `:history`
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command as Proc, Stdio};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+
+const HISTORY_FILE: &str = ".yalla/history.jsonl";
+
+/// One execution of a task, as written to the history file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Run {
+    /// Dotted path of the task
+    pub task: String,
+    /// Arguments given on the command line, replayed by `--rerun`
+    pub args: Vec<String>,
+    /// The command as split into program and arguments
+    pub argv: Vec<String>,
+    pub cwd: String,
+    /// UTC, RFC 3339
+    pub started: String,
+    pub finished: String,
+    pub duration_ms: u64,
+    pub exit_code: i32,
+    /// `HEAD` of the git repository the task ran in, if any
+    pub git_rev: Option<String>,
+}
+
+impl Run {
+    /// A run of `task` that started at `started` and just finished with `exit_code`.
+    pub fn finished(
+        task: &str,
+        args: &[String],
+        cmd: &str,
+        started: SystemTime,
+        exit_code: i32,
+    ) -> Self {
+        let finished = SystemTime::now();
+        Run {
+            task: task.to_string(),
+            args: args.to_vec(),
+            argv: shlex::split(cmd).unwrap_or_default(),
+            cwd: std::env::current_dir()
+                .map(|d| d.display().to_string())
+                .unwrap_or_default(),
+            started: timestamp(started),
+            finished: timestamp(finished),
+            duration_ms: finished
+                .duration_since(started)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            exit_code,
+            git_rev: git_rev(),
+        }
+    }
+}
+
+/// Append `run` to the history file.
+pub fn record(run: &Run) -> anyhow::Result<()> {
+    let path = Path::new(HISTORY_FILE);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).context(format!("creating {}", dir.display()))?;
+    }
+    let mut line = serde_json::to_string(run)?;
+    line.push('\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut f| f.write_all(line.as_bytes()))
+        .context(format!("writing {HISTORY_FILE}"))
+}
+
+/// Every recorded run, oldest first; lines that do not parse are skipped.
+pub fn load() -> anyhow::Result<Vec<Run>> {
+    match fs::read_to_string(HISTORY_FILE) {
+        Ok(text) => Ok(text
+            .lines()
+            .filter_map(|l| serde_json::from_str(l).ok())
+            .collect()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e).context(format!("reading {HISTORY_FILE}")),
+    }
+}
+
+/// The `n`th most recent run, counting from 1 as `render` numbers them.
+pub fn nth_recent(runs: &[Run], n: usize) -> anyhow::Result<&Run> {
+    n.checked_sub(1)
+        .and_then(|i| runs.iter().rev().nth(i))
+        .ok_or_else(|| anyhow!("No run #{n} in the history ({} recorded)", runs.len()))
+}
+
+/// The `limit` most recent runs, newest first and numbered for `--rerun`.
+pub fn render(runs: &[Run], limit: usize) -> String {
+    if runs.is_empty() {
+        return "No runs recorded yet\n".to_string();
+    }
+    let recent: Vec<&Run> = runs.iter().rev().take(limit).collect();
+    let tasks: Vec<String> = recent
+        .iter()
+        .map(
+            |r| match shlex::try_join(r.args.iter().map(String::as_str)) {
+                Ok(args) if !args.is_empty() => format!("{} {args}", r.task),
+                _ => r.task.clone(),
+            },
+        )
+        .collect();
+    let mut out = format!(
+        "{:>3}  {:<20}  {:>4}  {:>8}  TASK\n",
+        "#", "STARTED", "EXIT", "TOOK"
+    );
+    for (i, (r, task)) in recent.iter().zip(&tasks).enumerate() {
+        out.push_str(&format!(
+            "{:>3}  {:<20}  {:>4}  {:>8}  {task}\n",
+            i + 1,
+            r.started.replace('T', " "),
+            r.exit_code,
+            duration(Duration::from_millis(r.duration_ms)),
+        ));
+    }
+    out
+}
+
+/// `850ms`, `12.3s` or `2m05s`
+pub fn duration(d: Duration) -> String {
+    let ms = d.as_millis();
+    match ms {
+        0..1_000 => format!("{ms}ms"),
+        1_000..60_000 => format!("{:.1}s", d.as_secs_f64()),
+        _ => format!("{}m{:02}s", ms / 60_000, ms / 1_000 % 60),
+    }
+}
+
+/// `t` in UTC as RFC 3339, e.g. `2026-10-18T09:30:00Z`
+// Seconds precision is plenty for a history, and needs no date library
+pub fn timestamp(t: SystemTime) -> String {
+    let secs = t.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, rem) = ((secs / 86_400) as i64, secs % 86_400);
+    // Days since 1970-01-01 to a civil date (Howard Hinnant's `civil_from_days`)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    )
+}
+
+fn git_rev() -> Option<String> {
+    let out = Proc::new("git")
+        .args(["rev-parse", "HEAD"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let rev = String::from_utf8(out.stdout).ok()?;
+    (out.status.success() && !rev.trim().is_empty()).then(|| rev.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(task: &str, args: &[&str], exit_code: i32) -> Run {
+        Run {
+            task: task.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+            argv: Vec::new(),
+            cwd: "/repo".to_string(),
+            started: "2026-10-18T09:30:00Z".to_string(),
+            finished: "2026-10-18T09:30:02Z".to_string(),
+            duration_ms: 1_500,
+            exit_code,
+            git_rev: None,
+        }
+    }
+
+    #[test]
+    fn timestamps_are_utc_rfc3339() {
+        let at = |secs| timestamp(UNIX_EPOCH + Duration::from_secs(secs));
+        assert_eq!(at(0), "1970-01-01T00:00:00Z");
+        assert_eq!(at(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(at(1_700_000_000), "2023-11-14T22:13:20Z");
+    }
+
+    #[test]
+    fn render_numbers_newest_first() {
+        let runs = [
+            run("ci.test", &[], 0),
+            run("db.migrate", &["--to", "v 2"], 1),
+        ];
+        assert_eq!(
+            render(&runs, 10),
+            "  #  STARTED               EXIT      TOOK  TASK\n  \
+             1  2026-10-18 09:30:00Z     1      1.5s  db.migrate --to 'v 2'\n  \
+             2  2026-10-18 09:30:00Z     0      1.5s  ci.test\n"
+        );
+        assert_eq!(nth_recent(&runs, 2).unwrap().task, "ci.test");
+        assert!(nth_recent(&runs, 0).is_err());
+        assert!(nth_recent(&runs, 3).is_err());
+    }
+
+    #[test]
+    fn durations_pick_a_readable_unit() {
+        assert_eq!(duration(Duration::from_millis(850)), "850ms");
+        assert_eq!(duration(Duration::from_millis(12_340)), "12.3s");
+        assert_eq!(duration(Duration::from_secs(125)), "2m05s");
+    }
+}
@@ -0,0 +1,60 @@
+//! # Yalla - A Namespaced Task Runner
+//!
+//! The `yalla` binary is a thin layer over this library, which loads a Yallafile into a
+//! tree of [`CmdNode`]s, finds tasks in it and runs them. Other tools can do the same:
+//!
+//! ```
+//! let root = yalla::parse_root(
+//!     "yalla",
+//!     "Yallafile",
+//!     r#"
+//!     [ci.test]
+//!     description = "Run the tests"
+//!     cmd = "cargo test"
+//!     "#,
+//! )?;
+//! let test = yalla::find_node(&root, &yalla::split_path("ci.test")).unwrap();
+//! assert_eq!(test.cmd.as_deref(), Some("cargo test"));
+//! assert_eq!(test.source.as_ref().map(|s| s.line), Some(2));
+//! # Ok::<(), yalla::YallaError>(())
+//! ```
+//!
+//! Errors are [`YallaError`]s; problems in the file carry a [`Diagnostic`] whose `Display`
+//! shows the offending line:
+//!
+//! ```
+//! let err = yalla::parse_root("yalla", "Yallafile", "[build]\ncmd = 42\n").unwrap_err();
+//! assert!(matches!(err, yalla::YallaError::Invalid(_)));
+//! assert!(err.to_string().starts_with("Yallafile:2:7: `cmd` must be a string"));
+//! ```
+mod builtin;
+mod cache;
+mod check;
+mod clap_util;
+#[doc(hidden)]
+pub mod cli;
+mod diagnostic;
+mod docs;
+mod error;
+mod guard;
+mod history;
+mod list;
+mod model;
+mod output;
+mod pick;
+mod process;
+mod prompt;
+mod report;
+mod resolve;
+mod run;
+mod schema;
+mod toml;
+mod watch;
+
+pub use crate::diagnostic::{Diagnostic, Snippet};
+pub use crate::error::YallaError;
+pub use crate::guard::Requirement;
+pub use crate::model::{CmdNode, Example, Location, OnUnmet, Stdin, find_node, split_path};
+pub use crate::output::Output;
+pub use crate::run::{Options, run_task};
+pub use crate::toml::{YALLAFILE, load_root, parse_root, table_to_root};
@@ -0,0 +1,148 @@
+//! Listing of the task tree as a tree, flat dotted paths, or JSON
+use serde::Serialize;
+
+use crate::model::{self, CmdNode};
+use crate::schema::DUMP_VERSION;
+
+/// Output of `:dump`: the fully resolved tree, versioned by `schema_version`
+#[derive(Debug, Serialize)]
+pub struct Dump<'a> {
+    pub schema_version: u32,
+    pub root: &'a CmdNode,
+}
+
+pub fn dump(root: &CmdNode) -> String {
+    let dump = Dump {
+        schema_version: DUMP_VERSION,
+        root,
+    };
+    let mut json = serde_json::to_string_pretty(&dump).expect("tree serializes");
+    json.push('\n');
+    json
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Tree,
+    Flat,
+    Json,
+}
+
+/// One node of the tree as exposed by `--json`
+#[derive(Debug, Serialize)]
+pub struct Entry {
+    pub name: String,
+    pub path: String,
+    pub description: Option<String>,
+    pub cmd: Option<String>,
+    pub runnable: bool,
+    pub namespace: bool,
+    pub aliases: Vec<String>,
+    pub alias_of: Option<String>,
+    pub hidden: bool,
+    pub private: bool,
+}
+
+/// Render the children of the node at `path` (real names). Hidden and private tasks need `all`.
+pub fn render(root: &CmdNode, path: &[String], format: Format, all: bool) -> String {
+    match format {
+        Format::Tree => {
+            let node = model::find_node(root, path).expect("path was resolved by the caller");
+            let mut lines = Vec::new();
+            tree_lines(node, "", true, all, &mut lines);
+            let width = lines.iter().map(|(l, _)| l.chars().count()).max();
+            let mut out = String::new();
+            for (label, desc) in &lines {
+                match desc {
+                    Some(d) => {
+                        let pad = width.unwrap_or(0) - label.chars().count();
+                        out.push_str(&format!("{label}{}  {d}\n", " ".repeat(pad)));
+                    }
+                    None => out.push_str(&format!("{label}\n")),
+                }
+            }
+            out
+        }
+        Format::Flat => entries(root, path, all)
+            .iter()
+            .map(|e| format!("{}\n", e.path))
+            .collect(),
+        Format::Json => {
+            let mut json =
+                serde_json::to_string_pretty(&entries(root, path, all)).expect("entries serialize");
+            json.push('\n');
+            json
+        }
+    }
+}
+
+/// All nodes below the one at `path` (real names), depth first, in display order.
+pub fn entries(root: &CmdNode, path: &[String], all: bool) -> Vec<Entry> {
+    fn walk(root: &CmdNode, n: &CmdNode, path: &mut Vec<String>, all: bool, out: &mut Vec<Entry>) {
+        for ch in visible(n, all) {
+            path.push(ch.name.clone());
+            out.push(Entry {
+                name: ch.name.clone(),
+                path: path.join("."),
+                description: ch.description.clone(),
+                cmd: ch.cmd.clone(),
+                // An alias runs whatever it points at, which may be a namespace
+                runnable: match &ch.alias_of {
+                    Some(target) => model::find_node(root, &model::split_path(target))
+                        .is_some_and(|t| t.cmd.is_some()),
+                    None => ch.cmd.is_some(),
+                },
+                namespace: !ch.children.is_empty(),
+                aliases: ch.aliases.clone(),
+                alias_of: ch.alias_of.clone(),
+                hidden: ch.hidden,
+                private: ch.private,
+            });
+            walk(root, ch, path, all, out);
+            path.pop();
+        }
+    }
+    let mut out = Vec::new();
+    if let Some(node) = model::find_node(root, path) {
+        walk(root, node, &mut path.to_vec(), all, &mut out);
+    }
+    out
+}
+
+/// Children shown in help and listings; hidden and private ones need `all`
+pub fn visible(n: &CmdNode, all: bool) -> Vec<&CmdNode> {
+    n.sorted_children()
+        .into_iter()
+        .filter(|c| all || !(c.hidden || c.private))
+        .collect()
+}
+
+// Top-level entries start at the margin; deeper ones get box-drawing branches
+fn tree_lines(
+    n: &CmdNode,
+    indent: &str,
+    top: bool,
+    all: bool,
+    out: &mut Vec<(String, Option<String>)>,
+) {
+    let kids = visible(n, all);
+    for (i, ch) in kids.iter().enumerate() {
+        let last = i + 1 == kids.len();
+        let (branch, next) = match (top, last) {
+            (true, _) => ("", ""),
+            (false, false) => ("├── ", "│   "),
+            (false, true) => ("└── ", "    "),
+        };
+        let mut label = format!("{indent}{branch}{}", ch.name);
+        if !ch.aliases.is_empty() {
+            label.push_str(&format!(" ({})", ch.aliases.join(", ")));
+        }
+        if ch.private {
+            label.push_str(" [private]");
+        } else if ch.hidden {
+            label.push_str(" [hidden]");
+        }
+        out.push((label, ch.description.clone()));
+        tree_lines(ch, &format!("{indent}{next}"), false, all, out);
+    }
+}
@@ -1,58 +1,306 @@
-/// Internal command tree model independent of clap
+//! The task tree a Yallafile describes, independent of clap
 use clap::{Arg, Command};
+use clap_complete::engine::{ArgValueCandidates, CompletionCandidate};
+use serde::Serialize;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+use crate::guard;
+
+/// A task or namespace; the root node stands for the program itself
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
 pub struct CmdNode {
     pub name: String,
     pub description: Option<String>,
+    /// Extended help shown by `--help` (but not `-h`)
+    pub long_description: Option<String>,
     pub cmd: Option<String>,
     pub children: Vec<CmdNode>,
+    /// Glob patterns of the task's inputs: it is skipped while they are unchanged since its
+    /// last successful run, or older than all of its `outputs`
+    pub sources: Vec<String>,
+    pub outputs: Vec<String>,
+    /// Glob patterns watched for changes by `--watch`, instead of `sources`
+    pub watch: Vec<String>,
+    /// File the task's output is copied to, with `{{task}}` and `{{timestamp}}` filled in
+    pub log: Option<String>,
+    pub stdin: Stdin,
+    /// Print the timing summary after the task runs, as `--time` does; at the top level of
+    /// the Yallafile, after every task
+    pub time: bool,
+    /// Checked before the task runs: programs that must be on PATH, environment variables
+    /// that must be set, a command that must succeed (`if`) and the operating systems it
+    /// runs on; `on_unmet` says whether failing them skips the task or is an error
+    pub requires: Vec<String>,
+    pub requires_env: Vec<String>,
+    #[serde(rename = "if")]
+    pub condition: Option<String>,
+    pub platforms: Vec<String>,
+    pub on_unmet: OnUnmet,
+    /// How to install the task's program, shown when it is not found
+    pub install_hint: Option<String>,
+    /// Question asked before running; refused non-interactively without `--yes`
+    pub confirm: Option<String>,
+    /// Seconds the task may run before it is killed and fails
+    pub timeout: Option<u64>,
+    /// Help section the task is listed under, and its position among its siblings
+    pub group: Option<String>,
+    pub order: Option<i64>,
+    /// Extra names for this node, shown in help
+    pub aliases: Vec<String>,
+    /// Set on entries of the top-level `[aliases]` table: the dotted path they stand for
+    pub alias_of: Option<String>,
+    /// Left out of help (also implied by a leading underscore); still runnable
+    pub hidden: bool,
+    /// Hidden, and only runnable by other tasks: a `yalla` started from a task's command
+    pub private: bool,
+    /// Values suggested when completing the task's arguments
+    pub choices: Vec<String>,
+    /// Shown at the end of the task's help
+    pub examples: Vec<Example>,
+    pub see_also: Vec<String>,
+    /// Where the node is defined, when loaded from a file
+    pub source: Option<Location>,
+}
+
+/// An entry of a task's `examples`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct Example {
+    pub cmd: String,
+    pub desc: Option<String>,
 }
 
-// Minimal string interner to satisfy clap's `'static` requirement for names
-fn safe_intern(s: &str) -> &'static str {
-    use std::collections::HashMap;
-    use std::sync::{LazyLock, Mutex};
-    static INTERN: LazyLock<Mutex<HashMap<String, &'static str>>> =
-        LazyLock::new(|| Mutex::new(HashMap::new()));
-    let mut map = INTERN.lock().unwrap();
-    if let Some(&r) = map.get(s) {
-        return r;
+/// Where a node is defined
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct Location {
+    pub file: String,
+    pub line: usize,
+}
+
+impl CmdNode {
+    /// Children in display order: those with an `order` first, lowest first, then the rest
+    /// as stored (by name, or as declared when the Yallafile sets `preserve_order`)
+    pub fn sorted_children(&self) -> Vec<&CmdNode> {
+        let mut kids: Vec<&CmdNode> = self.children.iter().collect();
+        kids.sort_by_key(|k| (k.order.is_none(), k.order));
+        kids
     }
-    let leaked: &'static str = Box::leak(s.to_string().into_boxed_str());
-    map.insert(s.to_string(), leaked);
-    leaked
+}
+
+/// What to do when a task's guards are not satisfied
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum OnUnmet {
+    #[default]
+    Fail,
+    Skip,
+}
+
+/// What a task reads on its standard input
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum Stdin {
+    /// yalla's own stdin, usually the terminal
+    #[default]
+    Inherit,
+    /// Nothing: reads see end of file at once
+    Null,
+    /// The contents of this file
+    File(String),
+    /// This text
+    Text(String),
 }
 
 pub fn build_clap_from_root(root: &CmdNode) -> Command {
-    fn to_cmd(n: &CmdNode) -> Command {
-        let mut c = Command::new(safe_intern(&n.name));
-        if let Some(d) = &n.description {
-            c = c.about(d.clone());
+    fn to_cmd(n: &CmdNode, root: &CmdNode) -> Command {
+        // `[aliases]` entries mirror their target under a new name
+        if let Some(target) = n
+            .alias_of
+            .as_deref()
+            .and_then(|t| walk(root, &split_path(t)))
+        {
+            return to_cmd(target, root)
+                .name(n.name.clone())
+                .about(n.description.clone().unwrap_or_default());
         }
 
-        // Stable order for help/tests
-        let mut kids = n.children.clone();
-        kids.sort_by(|a, b| a.name.cmp(&b.name));
-        for ch in &kids {
-            c = c.subcommand(to_cmd(ch));
+        let mut c = Command::new(n.name.clone()).hide(n.hidden || n.private);
+        for a in &n.aliases {
+            c = c.visible_alias(a.clone());
+        }
+        // Mark tasks that cannot run on this machine
+        let unmet: Vec<String> = guard::static_unmet(n).iter().map(|u| u.label()).collect();
+        match (&n.description, unmet.is_empty()) {
+            (Some(d), true) => c = c.about(d.clone()),
+            (Some(d), false) => c = c.about(format!("{d} [unmet: {}]", unmet.join(", "))),
+            (None, false) => c = c.about(format!("[unmet: {}]", unmet.join(", "))),
+            (None, true) => {}
+        }
+        if let Some(long) = &n.long_description {
+            let short = n.description.as_deref().map(|d| format!("{d}\n\n"));
+            c = c.long_about(format!("{}{long}", short.unwrap_or_default()));
         }
+        if let Some(footer) = help_footer(n, root) {
+            c = c.after_help(footer);
+        }
+
+        let kids = n.sorted_children();
+        let subs: Vec<Command> = kids.iter().map(|ch| to_cmd(ch, root)).collect();
+        if kids
+            .iter()
+            .any(|ch| ch.group.is_some() && !(ch.hidden || ch.private))
+        {
+            let template = grouped_help_template(&c, &kids, &subs, n.cmd.is_some());
+            c = c.help_template(template);
+        }
+        c = c.subcommands(subs);
 
         if n.cmd.is_some() {
-            c = c.arg(Arg::new("args").trailing_var_arg(true).num_args(0..));
+            let mut args = Arg::new("args").trailing_var_arg(true).num_args(0..);
+            if !n.choices.is_empty() {
+                let choices = n.choices.clone();
+                args = args.add(ArgValueCandidates::new(move || {
+                    choices.iter().map(CompletionCandidate::new).collect()
+                }));
+            }
+            c = c.arg(args);
         } else if !kids.is_empty() {
             c = c.subcommand_required(true).arg_required_else_help(true);
         }
         c
     }
 
-    to_cmd(root)
+    to_cmd(root, root)
+}
+
+// clap lists every subcommand under a single heading, so namespaces with grouped
+// children get one section per heading: ungrouped tasks first, then each group in
+// the order it first appears. clap still lays out the rows of each section.
+fn grouped_help_template(
+    c: &Command,
+    kids: &[&CmdNode],
+    subs: &[Command],
+    runnable: bool,
+) -> String {
+    let ungrouped = c.get_subcommand_help_heading().unwrap_or("Commands");
+    let mut sections: Vec<(&str, Vec<Command>)> = vec![(ungrouped, Vec::new())];
+    for (ch, sub) in kids.iter().zip(subs) {
+        let heading = ch.group.as_deref().unwrap_or(ungrouped);
+        match sections.iter_mut().find(|(h, _)| *h == heading) {
+            Some((_, cmds)) => cmds.push(sub.clone()),
+            None => sections.push((heading, vec![sub.clone()])),
+        }
+    }
+
+    let header = c.get_styles().get_header();
+    let mut t = String::from("{before-help}{about-with-newline}\n{usage-heading} {usage}\n");
+    for (i, (heading, cmds)) in sections.into_iter().enumerate() {
+        // Only the first section gets the `help` subcommand clap adds
+        let rows = Command::new(c.get_name().to_string())
+            .styles(c.get_styles().clone())
+            .help_template("{subcommands}")
+            .disable_help_subcommand(i > 0)
+            .subcommands(cmds)
+            .render_help()
+            .ansi()
+            .to_string();
+        let rows = rows.trim_end();
+        if !rows.is_empty() {
+            t.push_str(&format!("\n{header}{heading}:{header:#}\n{rows}\n"));
+        }
+    }
+    if runnable {
+        t.push_str(&format!(
+            "\n{header}Arguments:{header:#}\n{{positionals}}\n"
+        ));
+    }
+    t.push_str(&format!(
+        "\n{header}Options:{header:#}\n{{options}}{{after-help}}"
+    ));
+    t
+}
+
+// The command, examples and related tasks, listed after the options
+fn help_footer(n: &CmdNode, root: &CmdNode) -> Option<String> {
+    let mut sections = Vec::new();
+    if let Some(cmd) = &n.cmd {
+        sections.push(format!("Command:\n  {cmd}"));
+    }
+    if !n.examples.is_empty() {
+        let mut s = String::from("Examples:");
+        for ex in &n.examples {
+            s.push_str(&format!("\n  {}", ex.cmd));
+            if let Some(desc) = &ex.desc {
+                s.push_str(&format!("\n      {desc}"));
+            }
+        }
+        sections.push(s);
+    }
+    if !n.see_also.is_empty() {
+        let refs: Vec<String> = n.see_also.iter().map(|r| see_also_ref(root, r)).collect();
+        sections.push(format!("See also: {}", refs.join(", ")));
+    }
+    (!sections.is_empty()).then(|| sections.join("\n\n"))
 }
 
+/// A `see_also` entry as shown to users: task paths become `yalla a b`, anything else is kept.
+pub fn see_also_ref(root: &CmdNode, entry: &str) -> String {
+    let path = split_path(entry);
+    match canonical_path(root, &path) {
+        Some(_) => format!("yalla {}", path.join(" ")),
+        None => entry.to_string(),
+    }
+}
+
+/// `"ci.test"` as the path `["ci", "test"]`.
+pub fn split_path(dotted: &str) -> Vec<String> {
+    dotted.split('.').map(|s| s.to_string()).collect()
+}
+
+/// Resolve `path`, which may use aliases, to the real names of the nodes it reaches.
+pub fn canonical_path(root: &CmdNode, path: &[String]) -> Option<Vec<String>> {
+    let mut cur = root;
+    let mut out = Vec::new();
+    for seg in path {
+        let next = cur
+            .children
+            .iter()
+            .find(|c| &c.name == seg || c.aliases.contains(seg))?;
+        match &next.alias_of {
+            Some(target) => {
+                out = split_path(target);
+                cur = walk(root, &out)?;
+            }
+            None => {
+                out.push(next.name.clone());
+                cur = next;
+            }
+        }
+    }
+    Some(out)
+}
+
+/// Whether the node at `path` (real names) or one of its ancestors is private.
+pub fn is_private(root: &CmdNode, path: &[String]) -> bool {
+    (1..=path.len()).any(|i| walk(root, &path[..i]).is_some_and(|n| n.private))
+}
+
+/// The node at `path`, which may use aliases.
 pub fn find_node<'a>(root: &'a CmdNode, path: &[String]) -> Option<&'a CmdNode> {
+    walk(root, &canonical_path(root, path)?)
+}
+
+// Follow real names only
+fn walk<'a>(root: &'a CmdNode, path: &[String]) -> Option<&'a CmdNode> {
     let mut cur = root;
     for seg in path {
-        cur = cur.children.iter().find(|c| &c.name == seg)?;
+        cur = cur
+            .children
+            .iter()
+            .find(|c| &c.name == seg && c.alias_of.is_none())?;
     }
     Some(cur)
 }
@@ -74,6 +322,7 @@ mod tests {
                     description: Some("Stop local services".to_string()),
                     cmd: Some("docker compose down".to_string()),
                     children: vec![],
+                    ..Default::default()
                 },
                 // B) Explicit parent + children
                 CmdNode {
@@ -86,6 +335,7 @@ mod tests {
                             description: None,
                             cmd: Some("cargo fmt".to_string()),
                             children: vec![],
+                            ..Default::default()
                         },
                         CmdNode {
                             name: "lint".to_string(),
@@ -95,14 +345,17 @@ mod tests {
                                     .to_string(),
                             ),
                             children: vec![],
+                            ..Default::default()
                         },
                         CmdNode {
                             name: "ls".to_string(),
                             description: None,
                             cmd: Some("ls -1".to_string()),
                             children: vec![],
+                            ..Default::default()
                         },
                     ],
+                    ..Default::default()
                 },
                 // C) Parent with cmd AND children
                 CmdNode {
@@ -115,14 +368,17 @@ mod tests {
                             description: None,
                             cmd: Some("bash ./scripts/deploy.sh --env=prod".to_string()),
                             children: vec![],
+                            ..Default::default()
                         },
                         CmdNode {
                             name: "test".to_string(),
                             description: None,
                             cmd: Some("cargo test --all --all-features".to_string()),
                             children: vec![],
+                            ..Default::default()
                         },
                     ],
+                    ..Default::default()
                 },
                 // D) Implicit parent (single child)
                 CmdNode {
@@ -134,7 +390,9 @@ mod tests {
                         description: None,
                         cmd: Some("diesel migration run".to_string()),
                         children: vec![],
+                        ..Default::default()
                     }],
+                    ..Default::default()
                 },
                 // E) Implicit parent (multiple children)
                 CmdNode {
@@ -147,14 +405,17 @@ mod tests {
                             description: None,
                             cmd: Some("cargo build --release".to_string()),
                             children: vec![],
+                            ..Default::default()
                         },
                         CmdNode {
                             name: "test".to_string(),
                             description: None,
                             cmd: Some("cargo test --workspace".to_string()),
                             children: vec![],
+                            ..Default::default()
                         },
                     ],
+                    ..Default::default()
                 },
                 // F) Deep implicit parents
                 CmdNode {
@@ -170,8 +431,11 @@ mod tests {
                             description: None,
                             cmd: Some("kubectl apply -k k8s/overlays/dev".to_string()),
                             children: vec![],
+                            ..Default::default()
                         }],
+                        ..Default::default()
                     }],
+                    ..Default::default()
                 },
                 // G) Another realistic leaf under a different namespace
                 CmdNode {
@@ -183,9 +447,12 @@ mod tests {
                         description: None,
                         cmd: Some("git rev-parse --is-inside-work-tree".to_string()),
                         children: vec![],
+                        ..Default::default()
                     }],
+                    ..Default::default()
                 },
             ],
+            ..Default::default()
         }
     }
 
@@ -251,5 +518,131 @@ mod tests {
             let res = cmd.clone().try_get_matches_from(["yalla", "unknown"]);
             assert!(res.is_err());
         }
+
+        #[test]
+        fn rebuilding_uses_each_trees_own_names() {
+            // Reloading a config must not keep names from earlier trees around
+            for i in 0..1000 {
+                let mut root = fixture_root();
+                root.children[0].name = format!("stop-{i}");
+                root.children[0].aliases = vec![format!("s{i}")];
+                let cmd = build_clap_from_root(&root);
+                let stop = cmd.find_subcommand(format!("s{i}")).unwrap();
+                assert_eq!(stop.get_name(), format!("stop-{i}"));
+                drop(root);
+                let m = cmd.get_matches_from(["yalla".to_string(), format!("stop-{i}")]);
+                assert_eq!(subcommand_path(&m), vec![format!("stop-{i}")]);
+            }
+        }
+    }
+
+    mod aliases {
+        use super::*;
+
+        fn aliased_root() -> CmdNode {
+            let mut root = fixture_root();
+            let tools = root
+                .children
+                .iter_mut()
+                .find(|c| c.name == "tools")
+                .unwrap();
+            tools.children[1].aliases = vec!["l".to_string()];
+            root.children.push(CmdNode {
+                name: "k".to_string(),
+                alias_of: Some("kube.dev".to_string()),
+                ..Default::default()
+            });
+            root
+        }
+
+        fn path(segs: &[&str]) -> Vec<String> {
+            segs.iter().map(|s| s.to_string()).collect()
+        }
+
+        #[test]
+        fn canonical_path_follows_node_and_table_aliases() {
+            let root = aliased_root();
+            assert_eq!(
+                canonical_path(&root, &path(&["tools", "l"])),
+                Some(path(&["tools", "lint"]))
+            );
+            assert_eq!(
+                canonical_path(&root, &path(&["k", "apply"])),
+                Some(path(&["kube", "dev", "apply"]))
+            );
+            assert_eq!(
+                find_node(&root, &path(&["k", "apply"])).and_then(|n| n.cmd.as_deref()),
+                Some("kubectl apply -k k8s/overlays/dev")
+            );
+        }
+
+        #[test]
+        fn clap_accepts_aliases() {
+            let cmd = build_clap_from_root(&aliased_root());
+            let m = cmd.clone().get_matches_from(["yalla", "tools", "l"]);
+            assert_eq!(subcommand_path(&m), path(&["tools", "lint"]));
+
+            let m = cmd.clone().get_matches_from(["yalla", "k", "apply"]);
+            assert_eq!(subcommand_path(&m), path(&["k", "apply"]));
+        }
+    }
+
+    mod help {
+        use super::*;
+
+        fn db(group: Option<&str>) -> CmdNode {
+            let leaf = |name: &str, group: Option<&str>| CmdNode {
+                name: name.to_string(),
+                description: Some(format!("{name} the database")),
+                cmd: Some(format!("./db.sh {name}")),
+                group: group.map(str::to_string),
+                ..Default::default()
+            };
+            CmdNode {
+                name: "yalla".to_string(),
+                children: vec![CmdNode {
+                    name: "db".to_string(),
+                    description: Some("Open a database shell".to_string()),
+                    cmd: Some("psql".to_string()),
+                    children: vec![leaf("reset", None), leaf("seed", group)],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }
+        }
+
+        fn help(root: &CmdNode) -> String {
+            let mut cmd = build_clap_from_root(root);
+            cmd.build();
+            let db = cmd.find_subcommand_mut("db").unwrap();
+            db.render_help().to_string()
+        }
+
+        #[test]
+        fn groups_get_their_own_section_and_keep_the_rest_of_the_help() {
+            let out = help(&db(Some("Data")));
+            assert!(out.contains("Usage: yalla db"), "{out}");
+            assert!(
+                out.contains("Commands:\n  reset  reset the database\n"),
+                "{out}"
+            );
+            assert!(
+                out.contains("\nData:\n  seed  seed the database\n"),
+                "{out}"
+            );
+            assert!(out.contains("Arguments:\n  [args]..."), "{out}");
+            assert!(out.ends_with("Command:\n  psql\n"), "{out}");
+        }
+
+        #[test]
+        fn without_groups_clap_lays_out_the_help() {
+            let out = help(&db(None));
+            assert!(
+                out.contains("Commands:\n  reset  reset the database\n  seed   seed"),
+                "{out}"
+            );
+            assert!(!out.contains("Data:"), "{out}");
+            assert!(out.ends_with("Command:\n  psql\n"), "{out}");
+        }
     }
 }
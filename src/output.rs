@@ -0,0 +1,237 @@
+//! Where a task's piped output goes: the terminal as-is, line by line with a `[task]`
+//! prefix, or held back and printed in one CI fold when the task finishes. A log file, if
+//! any, always gets the output unchanged.
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How a task's output is shown (`--output`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Output {
+    /// Straight through, as if yalla were not there
+    #[default]
+    Raw,
+    /// Every line starts with the task's path, e.g. `[ci.test] `
+    Prefixed,
+    /// Held back until the task finishes, then printed as one foldable CI section
+    Grouped,
+}
+
+impl Output {
+    pub(crate) fn from_name(name: &str) -> Self {
+        match name {
+            "prefixed" => Output::Prefixed,
+            "grouped" => Output::Grouped,
+            _ => Output::Raw,
+        }
+    }
+}
+
+/// Which CI's fold markers `Grouped` output is wrapped in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Markers {
+    /// `::group::` and `::endgroup::`
+    GitHub,
+    /// `section_start` and `section_end`
+    GitLab,
+}
+
+impl Markers {
+    /// GitLab's when running in GitLab CI, otherwise GitHub's, which other CIs
+    /// (and people reading a plain log) can also make sense of
+    pub fn from_env() -> Self {
+        match std::env::var_os("GITLAB_CI") {
+            Some(_) => Markers::GitLab,
+            None => Markers::GitHub,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// Takes the chunks a task writes to its stdout and stderr, in the order they are read,
+/// and passes them on according to the `Output` mode.
+pub struct Mux<O, E> {
+    task: String,
+    mode: Output,
+    markers: Markers,
+    started: SystemTime,
+    out: O,
+    err: E,
+    log: Option<Box<dyn Write + Send>>,
+    // Prefixed: the unfinished last line of each stream; Grouped: everything so far
+    pending: [Vec<u8>; 2],
+}
+
+impl<O: Write, E: Write> Mux<O, E> {
+    pub fn new(
+        task: &str,
+        mode: Output,
+        markers: Markers,
+        out: O,
+        err: E,
+        log: Option<Box<dyn Write + Send>>,
+    ) -> Self {
+        Mux {
+            task: task.to_string(),
+            mode,
+            markers,
+            started: SystemTime::now(),
+            out,
+            err,
+            log,
+            pending: [Vec::new(), Vec::new()],
+        }
+    }
+
+    /// Pass on `chunk`, just read from the task's `stream`. Write errors are ignored, so
+    /// that the task's pipes keep being drained when the terminal or the log went away.
+    pub fn write(&mut self, stream: Stream, chunk: &[u8]) {
+        if let Some(log) = &mut self.log {
+            let _ = log.write_all(chunk);
+        }
+        let _ = match self.mode {
+            Output::Raw => self.terminal(stream, chunk),
+            Output::Prefixed => {
+                let pending = &mut self.pending[stream as usize];
+                pending.extend_from_slice(chunk);
+                let Some(end) = pending.iter().rposition(|&b| b == b'\n') else {
+                    return;
+                };
+                let lines: Vec<u8> = pending.drain(..=end).collect();
+                let prefixed = prefix(&self.task, &lines);
+                self.terminal(stream, &prefixed)
+            }
+            // One buffer, so that stdout and stderr stay in the order they were written
+            Output::Grouped => {
+                self.pending[0].extend_from_slice(chunk);
+                Ok(())
+            }
+        };
+    }
+
+    /// The task has exited: print what was held back.
+    pub fn finish(&mut self) {
+        let _ = match self.mode {
+            Output::Raw => Ok(()),
+            Output::Prefixed => {
+                for stream in [Stream::Stdout, Stream::Stderr] {
+                    let rest = std::mem::take(&mut self.pending[stream as usize]);
+                    if !rest.is_empty() {
+                        let mut line = prefix(&self.task, &rest);
+                        line.push(b'\n');
+                        let _ = self.terminal(stream, &line);
+                    }
+                }
+                Ok(())
+            }
+            Output::Grouped => self.group(),
+        };
+    }
+
+    fn group(&mut self) -> io::Result<()> {
+        let mut body = std::mem::take(&mut self.pending[0]);
+        if body.last().is_some_and(|&b| b != b'\n') {
+            body.push(b'\n');
+        }
+        let task = &self.task;
+        let (start, end) = match self.markers {
+            Markers::GitHub => (format!("::group::{task}\n"), "::endgroup::\n".to_string()),
+            Markers::GitLab => {
+                // Section names may only hold letters, digits, `_`, `.` and `-`
+                let name: String = task
+                    .chars()
+                    .map(|c| match c {
+                        'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '.' | '-' => c,
+                        _ => '_',
+                    })
+                    .collect();
+                let secs = |t: SystemTime| t.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+                (
+                    format!(
+                        "\x1b[0Ksection_start:{}:{name}[collapsed=true]\r\x1b[0K{task}\n",
+                        secs(self.started)
+                    ),
+                    format!(
+                        "\x1b[0Ksection_end:{}:{name}\r\x1b[0K\n",
+                        secs(SystemTime::now())
+                    ),
+                )
+            }
+        };
+        self.out.write_all(start.as_bytes())?;
+        self.out.write_all(&body)?;
+        self.out.write_all(end.as_bytes())?;
+        self.out.flush()
+    }
+
+    fn terminal(&mut self, stream: Stream, bytes: &[u8]) -> io::Result<()> {
+        match stream {
+            Stream::Stdout => self.out.write_all(bytes).and_then(|()| self.out.flush()),
+            Stream::Stderr => self.err.write_all(bytes).and_then(|()| self.err.flush()),
+        }
+    }
+}
+
+// `lines` with `[task] ` in front of each line
+fn prefix(task: &str, lines: &[u8]) -> Vec<u8> {
+    let tag = format!("[{task}] ");
+    let mut out = Vec::with_capacity(lines.len() + tag.len());
+    for line in lines.split_inclusive(|&b| b == b'\n') {
+        out.extend_from_slice(tag.as_bytes());
+        out.extend_from_slice(line);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mux(mode: Output, markers: Markers) -> Mux<Vec<u8>, Vec<u8>> {
+        Mux::new("ci.test", mode, markers, Vec::new(), Vec::new(), None)
+    }
+
+    fn text(bytes: &[u8]) -> &str {
+        std::str::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn prefixed_tags_whole_lines_per_stream() {
+        let mut m = mux(Output::Prefixed, Markers::GitHub);
+        m.write(Stream::Stdout, b"compiling\nrunn");
+        m.write(Stream::Stderr, b"warning: unused\n");
+        m.write(Stream::Stdout, b"ing 3 tests\nok");
+        m.finish();
+        assert_eq!(
+            text(&m.out),
+            "[ci.test] compiling\n[ci.test] running 3 tests\n[ci.test] ok\n"
+        );
+        assert_eq!(text(&m.err), "[ci.test] warning: unused\n");
+    }
+
+    #[test]
+    fn grouped_prints_everything_at_the_end_in_one_fold() {
+        let mut m = mux(Output::Grouped, Markers::GitHub);
+        m.write(Stream::Stdout, b"one\n");
+        m.write(Stream::Stderr, b"two");
+        assert!(m.out.is_empty());
+        m.finish();
+        assert_eq!(text(&m.out), "::group::ci.test\none\ntwo\n::endgroup::\n");
+        assert!(m.err.is_empty());
+
+        let mut m = mux(Output::Grouped, Markers::GitLab);
+        m.write(Stream::Stdout, b"one\n");
+        m.finish();
+        let out = text(&m.out);
+        assert!(out.starts_with("\x1b[0Ksection_start:"), "{out:?}");
+        assert!(
+            out.contains(":ci.test[collapsed=true]\r\x1b[0Kci.test\none\n\x1b[0Ksection_end:"),
+            "{out:?}"
+        );
+    }
+}
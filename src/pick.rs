@@ -0,0 +1,155 @@
+//! Choosing a task from a fuzzy-filtered list (`yalla :pick`, `yalla -i`)
+//!
+//! On a terminal this is an interactive list. Otherwise the first line of stdin is
+//! the query (best match wins) and the optional second line holds the arguments,
+//! which keeps the picker scriptable and testable. The rest of stdin is left unread for
+//! the picked task.
+use std::fs::File;
+use std::io::{self, Read};
+
+use anyhow::anyhow;
+use dialoguer::{FuzzySelect, Input};
+use fuzzy_matcher::FuzzyMatcher;
+use fuzzy_matcher::skim::SkimMatcherV2;
+
+use crate::list;
+use crate::model::{self, CmdNode};
+use crate::prompt;
+
+/// A task the picker offers, by canonical path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub path: String,
+    pub description: Option<String>,
+}
+
+/// Runnable tasks shown in help, one per real task (`[aliases]` entries are left out).
+pub fn candidates(root: &CmdNode) -> Vec<Candidate> {
+    list::entries(root, &[], false)
+        .into_iter()
+        .filter(|e| e.cmd.is_some())
+        .map(|e| Candidate {
+            path: e.path,
+            description: e.description,
+        })
+        .collect()
+}
+
+/// The candidate matching `query` best; ties go to the one listed first.
+pub fn best_match<'a>(candidates: &'a [Candidate], query: &str) -> Option<&'a Candidate> {
+    let matcher = SkimMatcherV2::default();
+    let mut best: Option<(i64, &Candidate)> = None;
+    for c in candidates {
+        if let Some(score) = matcher.fuzzy_match(&c.path, query.trim())
+            && best.is_none_or(|(b, _)| score > b)
+        {
+            best = Some((score, c));
+        }
+    }
+    best.map(|(_, c)| c)
+}
+
+/// Ask for a task and its arguments; returns the task's path and the arguments.
+pub fn pick(root: &CmdNode) -> anyhow::Result<(Vec<String>, Vec<String>)> {
+    let candidates = candidates(root);
+    if candidates.is_empty() {
+        return Err(anyhow!("No tasks to pick from"));
+    }
+
+    let (path, args) = if prompt::interactive() {
+        let width = candidates.iter().map(|c| c.path.len()).max().unwrap_or(0);
+        let items: Vec<String> = candidates
+            .iter()
+            .map(|c| match &c.description {
+                Some(d) => format!("{:width$}  {d}", c.path),
+                None => c.path.clone(),
+            })
+            .collect();
+        let Some(i) = FuzzySelect::new()
+            .with_prompt("Task")
+            .items(&items)
+            .interact_opt()?
+        else {
+            return Err(anyhow!("No task picked"));
+        };
+        let path = candidates[i].path.clone();
+        let args: String = Input::new()
+            .with_prompt(format!("Arguments for {path}"))
+            .allow_empty(true)
+            .interact_text()?;
+        (path, args)
+    } else {
+        let mut stdin = unbuffered_stdin()?;
+        let query = read_line(&mut stdin)?;
+        let picked = best_match(&candidates, &query)
+            .ok_or_else(|| anyhow!("No task matches '{}'", query.trim()))?;
+        let args = read_line(&mut stdin)?;
+        (picked.path.clone(), args)
+    };
+
+    let args = shlex::split(&args).ok_or_else(|| anyhow!("Failed to parse arguments: {args}"))?;
+    Ok((model::split_path(&path), args))
+}
+
+// Our stdin without `io::Stdin`'s buffer, which would read ahead into input meant for the task
+fn unbuffered_stdin() -> io::Result<File> {
+    #[cfg(unix)]
+    let handle = std::os::fd::AsFd::as_fd(&io::stdin()).try_clone_to_owned()?;
+    #[cfg(windows)]
+    let handle = std::os::windows::io::AsHandle::as_handle(&io::stdin()).try_clone_to_owned()?;
+    Ok(File::from(handle))
+}
+
+// One line without its line ending, read a byte at a time so nothing after it is consumed;
+// empty at the end of input
+fn read_line(from: &mut impl Read) -> io::Result<String> {
+    let mut line = Vec::new();
+    let mut byte = [0];
+    loop {
+        match from.read(&mut byte) {
+            Ok(0) => break,
+            Ok(_) if byte[0] == b'\n' => break,
+            Ok(_) => line.push(byte[0]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    Ok(String::from_utf8_lossy(&line).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(path: &str) -> Candidate {
+        Candidate {
+            path: path.to_string(),
+            description: None,
+        }
+    }
+
+    #[test]
+    fn best_match_prefers_closest_path() {
+        let cs = [
+            candidate("ci.build"),
+            candidate("db.migrate"),
+            candidate("tools.lint"),
+        ];
+        assert_eq!(best_match(&cs, "dbmig"), Some(&cs[1]));
+        assert_eq!(best_match(&cs, "lint\n"), Some(&cs[2]));
+        assert_eq!(best_match(&cs, "zzz"), None);
+    }
+
+    #[test]
+    fn read_line_stops_at_the_newline() {
+        let mut input: &[u8] = b"dep\r\nstaging\nfor the task\n";
+        assert_eq!(read_line(&mut input).unwrap(), "dep");
+        assert_eq!(read_line(&mut input).unwrap(), "staging");
+        assert_eq!(input, b"for the task\n");
+        let mut empty: &[u8] = b"";
+        assert_eq!(read_line(&mut empty).unwrap(), "");
+    }
+}
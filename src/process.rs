@@ -1,31 +1,371 @@
-use std::process::{Command as Proc, ExitStatus};
+use std::fs::{self, File};
+use std::io::{self, Read, Stderr, Stdout, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command as Proc, ExitStatus, Stdio};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use anyhow::anyhow;
+use crate::error::YallaError;
+use crate::model::Stdin;
+use crate::output::{Markers, Mux, Output, Stream};
 
-pub fn execute(cmd: &str) -> anyhow::Result<ExitStatus> {
-    let mut parts = shlex::split(cmd).ok_or_else(|| anyhow!("Failed to parse command: {cmd}"))?;
+/// Set to the task's dotted path in the environment of every task yalla runs, so a `yalla`
+/// started by a task knows it may run private tasks
+pub const TASK_ENV: &str = "YALLA_TASK";
+
+/// How often a task with a `timeout` is checked on
+const POLL: Duration = Duration::from_millis(20);
+
+/// Run `cmd` for `task` with `stdin` as its input and wait for it; a nonzero exit or a
+/// signal is an error, as is running longer than `timeout`, after which the task is killed.
+///
+/// With a `log` file or an `output` other than `Raw`, the task's stdout and stderr are piped
+/// instead of inherited: copied to the log unchanged, and shown as `output` says.
+pub fn execute(
+    task: &str,
+    cmd: &str,
+    stdin: &Stdin,
+    log: Option<&Path>,
+    output: Output,
+    timeout: Option<Duration>,
+) -> Result<ExitStatus, YallaError> {
+    let failed = |source: io::Error| YallaError::Io {
+        context: format!("task '{task}': waiting for it to finish"),
+        source,
+    };
+    let (program, mut c) = command(cmd)?;
+    connect_stdin(task, &mut c, stdin)?;
+    let mux = if log.is_none() && output == Output::Raw {
+        None
+    } else {
+        let log = match log {
+            Some(log) => Some(create_log(log).map_err(|source| YallaError::Io {
+                context: format!("task '{task}': cannot create log {}", log.display()),
+                source,
+            })?),
+            None => None,
+        };
+        c.stdout(Stdio::piped()).stderr(Stdio::piped());
+        Some(Mux::new(
+            task,
+            output,
+            Markers::from_env(),
+            io::stdout(),
+            io::stderr(),
+            log.map(|f| Box::new(f) as Box<dyn Write + Send>),
+        ))
+    };
+    let mut child = start(task, program, c)?;
+    feed_stdin(&mut child, stdin);
+    let deadline = timeout.map(|t| Instant::now() + t);
+    let status = match mux {
+        None => wait(&mut child, deadline),
+        Some(mux) => tee(&mut child, mux, deadline),
+    }
+    .map_err(failed)?;
+    let Some(status) = status else {
+        return Err(YallaError::Timeout {
+            task: task.to_string(),
+            after: timeout.unwrap_or_default(),
+        });
+    };
+    if let Some(code) = status.code() {
+        return match code {
+            0 => Ok(status),
+            code => Err(YallaError::Exit {
+                task: task.to_string(),
+                code,
+            }),
+        };
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return Err(YallaError::Signal {
+                task: task.to_string(),
+                signal,
+            });
+        }
+    }
+    Err(YallaError::Exit {
+        task: task.to_string(),
+        code: 1,
+    })
+}
+
+/// `cmd` followed by `args`, quoted so each reaches the program as one argument.
+pub fn with_args(cmd: &str, args: &[String]) -> Result<String, YallaError> {
+    if args.is_empty() {
+        return Ok(cmd.to_string());
+    }
+    let quoted = shlex::try_join(args.iter().map(String::as_str)).map_err(|e| {
+        YallaError::InvalidCommand {
+            cmd: cmd.to_string(),
+            reason: format!("cannot pass the arguments: {e}"),
+        }
+    })?;
+    Ok(format!("{cmd} {quoted}"))
+}
+
+/// Start `cmd` for `task` with `stdin` as its input without waiting for it, e.g. so watch
+/// mode can kill and restart it.
+///
+/// On Unix the task gets a process group of its own, so that [`kill_group`] also stops the
+/// processes it started. A background group is stopped when it reads the terminal, so an
+/// inherited stdin that is a terminal is replaced by an empty one.
+pub fn spawn(task: &str, cmd: &str, stdin: &Stdin) -> Result<Child, YallaError> {
+    let (program, mut c) = command(cmd)?;
+    connect_stdin(task, &mut c, stdin)?;
+    #[cfg(unix)]
+    {
+        use std::io::IsTerminal;
+        use std::os::unix::process::CommandExt;
+        c.process_group(0);
+        if matches!(stdin, Stdin::Inherit) && io::stdin().is_terminal() {
+            c.stdin(Stdio::null());
+        }
+    }
+    let mut child = start(task, program, c)?;
+    feed_stdin(&mut child, stdin);
+    Ok(child)
+}
+
+/// Kill `child`, started by [`spawn`], together with its process group on Unix, and wait
+/// for it.
+pub fn kill_group(child: &mut Child) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        // The group outlives `child` if it left processes behind, so kill it either way.
+        // SAFETY: kill(2) only sends a signal; the negative pid names the group `spawn`
+        // created, whose id cannot be reused while any of its processes is alive.
+        if unsafe { libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL) } == -1 {
+            let e = io::Error::last_os_error();
+            if e.raw_os_error() != Some(libc::ESRCH) {
+                return Err(e);
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    if child.try_wait()?.is_none() {
+        child.kill()?;
+    }
+    child.wait()?;
+    Ok(())
+}
+
+fn connect_stdin(task: &str, c: &mut Proc, stdin: &Stdin) -> Result<(), YallaError> {
+    match stdin {
+        Stdin::Inherit => {}
+        Stdin::Null => {
+            c.stdin(Stdio::null());
+        }
+        Stdin::File(path) => {
+            let file = File::open(path).map_err(|source| YallaError::Io {
+                context: format!("task '{task}': cannot open stdin file {path}"),
+                source,
+            })?;
+            c.stdin(file);
+        }
+        Stdin::Text(_) => {
+            c.stdin(Stdio::piped());
+        }
+    }
+    Ok(())
+}
+
+// Write a `Stdin::Text` to the started child
+fn feed_stdin(child: &mut Child, stdin: &Stdin) {
+    if let (Stdin::Text(text), Some(mut input)) = (stdin, child.stdin.take()) {
+        // From a thread, so a task that writes before it has read everything cannot block
+        // on a full pipe; dropping `input` afterwards closes it
+        let text = text.clone();
+        thread::spawn(move || {
+            let _ = input.write_all(text.as_bytes());
+        });
+    }
+}
+
+/// Whether this process was started by a task, i.e. may run private tasks.
+pub fn in_task() -> bool {
+    std::env::var_os(TASK_ENV).is_some_and(|v| !v.is_empty())
+}
+
+fn start(task: &str, program: String, mut c: Proc) -> Result<Child, YallaError> {
+    c.env(TASK_ENV, task).spawn().map_err(|source| {
+        let suggestions = match source.kind() {
+            io::ErrorKind::NotFound => similar_programs(&program),
+            _ => Vec::new(),
+        };
+        YallaError::Spawn {
+            task: task.to_string(),
+            program,
+            source,
+            suggestions,
+            install_hint: None,
+        }
+    })
+}
+
+fn create_log(path: &Path) -> io::Result<File> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    File::create(path)
+}
+
+// Wait for `child` to exit, or kill it at `deadline`; `None` means it was killed
+fn wait(child: &mut Child, deadline: Option<Instant>) -> io::Result<Option<ExitStatus>> {
+    let Some(deadline) = deadline else {
+        return child.wait().map(Some);
+    };
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        match deadline.checked_duration_since(Instant::now()) {
+            Some(left) => thread::sleep(left.min(POLL)),
+            None => {
+                child.kill()?;
+                child.wait()?;
+                return Ok(None);
+            }
+        }
+    }
+}
+
+// `wait`, feeding the child's piped stdout and stderr to `mux` chunk by chunk as they are
+// read, so the two streams interleave about as they would on a terminal
+fn tee(
+    child: &mut Child,
+    mux: Mux<Stdout, Stderr>,
+    deadline: Option<Instant>,
+) -> io::Result<Option<ExitStatus>> {
+    let mux = Mutex::new(mux);
+    let (out, err) = (child.stdout.take(), child.stderr.take());
+    let status = thread::scope(|s| {
+        if let Some(out) = out {
+            s.spawn(|| forward(out, Stream::Stdout, &mux));
+        }
+        if let Some(err) = err {
+            s.spawn(|| forward(err, Stream::Stderr, &mux));
+        }
+        wait(child, deadline)
+    });
+    if let Ok(mut mux) = mux.into_inner() {
+        mux.finish();
+    }
+    status
+}
+
+fn forward(mut from: impl Read, stream: Stream, mux: &Mutex<Mux<Stdout, Stderr>>) {
+    let mut buf = [0; 8192];
+    loop {
+        let n = match from.read(&mut buf) {
+            Ok(0) => return,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => return,
+        };
+        if let Ok(mut mux) = mux.lock() {
+            mux.write(stream, &buf[..n]);
+        }
+    }
+}
+
+/// Run `cmd` silently and report whether it succeeded; failure to start counts as `false`.
+pub fn succeeds(cmd: &str) -> bool {
+    command(cmd).is_ok_and(|(_, mut c)| {
+        c.stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success())
+    })
+}
+
+// The program `cmd` starts, and the process to start it with
+fn command(cmd: &str) -> Result<(String, Proc), YallaError> {
+    let invalid = |reason: &str| YallaError::InvalidCommand {
+        cmd: cmd.to_string(),
+        reason: reason.to_string(),
+    };
+    let mut parts =
+        shlex::split(cmd).ok_or_else(|| invalid("unbalanced quotes or a trailing backslash"))?;
     if parts.is_empty() {
-        return Err(anyhow!("Empty command"));
+        return Err(invalid("it is empty"));
     }
     let program = parts.remove(0);
-    let status = Proc::new(&program).args(&parts).status()?;
-    if status.success() {
-        Ok(status)
-    } else {
-        Err(anyhow!(status))
+    let mut c = Proc::new(&program);
+    c.args(&parts);
+    Ok((program, c))
+}
+
+/// Locate `program` the way the OS would when spawning it.
+pub fn find_program(program: &str) -> Option<PathBuf> {
+    if program.contains(std::path::MAIN_SEPARATOR) || program.contains('/') {
+        let p = Path::new(program);
+        return is_executable(p).then(|| p.to_path_buf());
     }
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(program))
+        .find(|p| is_executable(p))
 }
 
-pub fn exit_code(status: ExitStatus) -> i32 {
-    if let Some(code) = status.code() {
-        return code;
+/// Programs on PATH named like `program`, closest first; empty for paths like `./run.sh`.
+pub fn similar_programs(program: &str) -> Vec<String> {
+    let Some(path) = std::env::var_os("PATH") else {
+        return Vec::new();
+    };
+    if program.contains(std::path::MAIN_SEPARATOR) || program.contains('/') {
+        return Vec::new();
     }
+    let names = std::env::split_paths(&path)
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flatten()
+        .flatten()
+        .filter(|entry| is_executable(&entry.path()))
+        .filter_map(|entry| entry.file_name().into_string().ok());
+    closest(program, names)
+}
+
+// At most three `names` close enough to `program` to be what was meant
+fn closest(program: &str, names: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut scored: Vec<(f64, String)> = names
+        .into_iter()
+        .filter(|n| n != program)
+        .map(|n| (strsim::jaro_winkler(program, &n), n))
+        .filter(|(score, _)| *score > 0.85)
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    // The same program can be in several PATH directories
+    scored.dedup_by(|a, b| a.1 == b.1);
+    scored.into_iter().take(3).map(|(_, n)| n).collect()
+}
+
+fn is_executable(p: &Path) -> bool {
     #[cfg(unix)]
     {
-        use std::os::unix::process::ExitStatusExt;
-        if let Some(sig) = status.signal() {
-            return 128 + sig;
-        }
+        use std::os::unix::fs::PermissionsExt;
+        p.metadata()
+            .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        p.is_file() || p.with_extension("exe").is_file()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closest_keeps_near_misses_best_first() {
+        let names = ["diesel2", "docker", "dieselx", "diesel", "ls", "diesel2"].map(String::from);
+        assert_eq!(closest("diesel", names), ["diesel2", "dieselx"]);
+        assert!(closest("kubectl", ["ls".to_string(), "cat".to_string()]).is_empty());
     }
-    1
 }
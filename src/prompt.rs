@@ -0,0 +1,19 @@
+//! Interactive prompts on the controlling terminal
+use std::io::{self, BufRead, IsTerminal, Write};
+
+/// Whether a human can answer prompts (stdin is a terminal).
+pub fn interactive() -> bool {
+    io::stdin().is_terminal()
+}
+
+/// Ask a yes/no `question` on stderr; anything but an explicit yes counts as no.
+pub fn confirm(question: &str) -> io::Result<bool> {
+    eprint!("{question} [y/N] ");
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(
+        answer.trim().to_ascii_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
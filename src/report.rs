@@ -0,0 +1,203 @@
+//! What a run did, for `--time` (a summary table) and `--report` (JUnit XML)
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::error::YallaError;
+use crate::history;
+
+/// One task of a run and how it ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    pub task: String,
+    pub status: Status,
+    pub duration: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Status {
+    Passed,
+    /// The error that stopped it
+    Failed(String),
+    /// Not run because it was up to date, with the reason
+    Cached(String),
+    /// Not run because its requirements are unmet and it sets `on_unmet = "skip"`
+    Skipped,
+}
+
+impl Status {
+    fn label(&self) -> &'static str {
+        match self {
+            Status::Passed => "ok",
+            Status::Failed(_) => "failed",
+            Status::Cached(_) => "cached",
+            Status::Skipped => "skipped",
+        }
+    }
+}
+
+/// A table of the outcomes, printed after the run by `--time`.
+pub fn summary(outcomes: &[Outcome]) -> String {
+    let rows: Vec<[String; 3]> = outcomes
+        .iter()
+        .map(|o| {
+            [
+                o.task.clone(),
+                o.status.label().to_string(),
+                history::duration(o.duration),
+            ]
+        })
+        .collect();
+    let width = |i: usize, header: &str| {
+        rows.iter()
+            .map(|r| r[i].len())
+            .chain([header.len()])
+            .max()
+            .unwrap_or(0)
+    };
+    let (task_w, status_w) = (width(0, "TASK"), width(1, "STATUS"));
+    let mut out = format!("{:task_w$}  {:status_w$}  TOOK\n", "TASK", "STATUS");
+    for [task, status, took] in &rows {
+        out.push_str(&format!("{task:task_w$}  {status:status_w$}  {took}\n"));
+    }
+    out
+}
+
+/// The outcomes as a JUnit XML test suite, one test case per task.
+pub fn junit(outcomes: &[Outcome]) -> String {
+    let count = |f: fn(&Status) -> bool| outcomes.iter().filter(|o| f(&o.status)).count();
+    let failures = count(|s| matches!(s, Status::Failed(_)));
+    let skipped = count(|s| matches!(s, Status::Cached(_) | Status::Skipped));
+    let total: Duration = outcomes.iter().map(|o| o.duration).sum();
+    let counts = format!(
+        "tests=\"{}\" failures=\"{failures}\" skipped=\"{skipped}\" time=\"{:.3}\"",
+        outcomes.len(),
+        total.as_secs_f64()
+    );
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!("<testsuites name=\"yalla\" {counts}>\n"));
+    xml.push_str(&format!("  <testsuite name=\"yalla\" {counts}>\n"));
+    for o in outcomes {
+        let case = format!(
+            "    <testcase name=\"{}\" classname=\"yalla\" time=\"{:.3}\"",
+            escape(&o.task),
+            o.duration.as_secs_f64()
+        );
+        let body = match &o.status {
+            Status::Passed => None,
+            Status::Failed(msg) => Some(format!("<failure message=\"{}\"/>", escape(msg))),
+            Status::Cached(reason) => Some(format!("<skipped message=\"{}\"/>", escape(reason))),
+            Status::Skipped => Some("<skipped message=\"requirements not met\"/>".to_string()),
+        };
+        match body {
+            None => xml.push_str(&format!("{case}/>\n")),
+            Some(body) => xml.push_str(&format!("{case}>\n      {body}\n    </testcase>\n")),
+        }
+    }
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+/// Write the JUnit report for `outcomes` to `path`.
+pub fn write_junit(path: &Path, outcomes: &[Outcome]) -> Result<(), YallaError> {
+    fs::write(path, junit(outcomes)).map_err(|source| YallaError::Io {
+        context: format!("writing {}", path.display()),
+        source,
+    })
+}
+
+// `s` as an XML attribute value. Terminal color codes are dropped, and other characters
+// XML 1.0 does not allow (control characters besides tab, LF and CR) become U+FFFD.
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\t' => out.push_str("&#9;"),
+            '\n' => out.push_str("&#10;"),
+            '\r' => out.push_str("&#13;"),
+            // An ANSI escape sequence such as `ESC [ 3 1 m`: up to and including its final byte
+            '\x1b' if chars.peek() == Some(&'[') => {
+                chars.next();
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            c if c < ' ' || c == '\u{fffe}' || c == '\u{ffff}' => out.push('\u{fffd}'),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcomes() -> Vec<Outcome> {
+        vec![
+            Outcome {
+                task: "ci.build".to_string(),
+                status: Status::Cached("sources unchanged since last successful run".to_string()),
+                duration: Duration::from_millis(3),
+            },
+            Outcome {
+                task: "ci.test".to_string(),
+                status: Status::Failed("ci.test failed with exit code 1 <see log>".to_string()),
+                duration: Duration::from_millis(1_250),
+            },
+        ]
+    }
+
+    #[test]
+    fn summary_lines_up_columns() {
+        assert_eq!(
+            summary(&outcomes()),
+            "TASK      STATUS  TOOK\n\
+             ci.build  cached  3ms\n\
+             ci.test   failed  1.2s\n"
+        );
+    }
+
+    #[test]
+    fn junit_reports_failures_and_skips() {
+        assert_eq!(
+            junit(&outcomes()),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="yalla" tests="2" failures="1" skipped="1" time="1.253">
+  <testsuite name="yalla" tests="2" failures="1" skipped="1" time="1.253">
+    <testcase name="ci.build" classname="yalla" time="0.003">
+      <skipped message="sources unchanged since last successful run"/>
+    </testcase>
+    <testcase name="ci.test" classname="yalla" time="1.250">
+      <failure message="ci.test failed with exit code 1 &lt;see log&gt;"/>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#
+        );
+    }
+
+    #[test]
+    fn junit_drops_color_codes_and_replaces_control_characters() {
+        let failed = Outcome {
+            task: "ci.lint".to_string(),
+            status: Status::Failed("\x1b[1;31merror\x1b[0m: bell\x07 and\tesc\x1b".to_string()),
+            duration: Duration::ZERO,
+        };
+        let xml = junit(&[failed]);
+        assert!(
+            xml.contains("<failure message=\"error: bell\u{fffd} and&#9;esc\u{fffd}\"/>"),
+            "{xml}"
+        );
+        assert!(!xml.chars().any(|c| c < ' ' && c != '\n'), "{xml:?}");
+    }
+}
@@ -0,0 +1,271 @@
+//! Shorthand for task paths, expanded before clap parses the command line
+//!
+//! `yalla kube.dev.apply` is the same as `yalla kube dev apply`, and inside a namespace
+//! a unique prefix stands for the full name (`yalla ku d a`). Runnable tasks only take
+//! exact names below them, so their own arguments are never mistaken for a prefix. A dotted
+//! word is a path throughout: once its first segment names a task, a later one that does
+//! not is an error rather than an argument, so a typo never runs the parent instead.
+use std::collections::VecDeque;
+
+use clap::Command;
+
+use crate::builtin;
+use crate::error::YallaError;
+use crate::model::{self, CmdNode};
+
+/// Rewrite `args` (without the program name) so every task path segment is a full name.
+/// `cli` is the root command, which tells which of the runner options take a value.
+pub fn expand_args(root: &CmdNode, cli: &Command, args: &[String]) -> anyhow::Result<Vec<String>> {
+    let mut out = Vec::new();
+    let mut rest = args.iter().peekable();
+
+    // Runner flags come first; built-ins and `help` parse their own arguments
+    while let Some(flag) = rest.next_if(|a| a.starts_with('-')) {
+        out.push(flag.clone());
+        // `--log-dir r` or `-o r`: `r` is the option's value, not a task
+        if takes_value(cli, flag)
+            && let Some(value) = rest.next()
+        {
+            out.push(value.clone());
+        }
+    }
+    let Some(first) = rest.next() else {
+        return Ok(out);
+    };
+    if builtin::is_builtin(first) || first == "help" {
+        out.push(first.clone());
+        out.extend(rest.cloned());
+        return Ok(out);
+    }
+
+    // Segments of the word being resolved; a dotted word yields several
+    let mut pending: VecDeque<String> = split_segment(root, first).into();
+    // Whether an earlier segment of that word named a task
+    let mut in_path = false;
+    let mut path = Vec::new();
+    let mut node = root;
+    loop {
+        let seg = match pending.pop_front() {
+            Some(seg) => seg,
+            // Options and `--` end the path
+            None => match rest.next_if(|a| !a.starts_with('-')) {
+                Some(next) => {
+                    pending.extend(split_segment(node, next));
+                    in_path = false;
+                    continue;
+                }
+                None => break,
+            },
+        };
+        match resolve_segment(node, &path, &seg)? {
+            Some(name) => {
+                path.push(name.clone());
+                out.push(name);
+                // An alias whose target does not resolve, e.g. one pointing at another alias
+                node = model::find_node(root, &path).ok_or_else(|| YallaError::UnknownTask {
+                    path: path.join("."),
+                })?;
+                in_path = true;
+            }
+            None if in_path => {
+                path.push(seg);
+                return Err(YallaError::UnknownTask {
+                    path: path.join("."),
+                }
+                .into());
+            }
+            None => {
+                // Not a task: the rest of the word is an argument (or an error clap reports)
+                let word: Vec<String> = std::iter::once(seg).chain(pending.drain(..)).collect();
+                out.push(word.join("."));
+                break;
+            }
+        }
+    }
+    out.extend(rest.cloned());
+    Ok(out)
+}
+
+// Whether `flag` is an option of `cli` whose value is the next argument
+fn takes_value(cli: &Command, flag: &str) -> bool {
+    let arg = match flag.strip_prefix("--") {
+        Some(long) if !long.contains('=') => {
+            cli.get_arguments().find(|a| a.get_long() == Some(long))
+        }
+        Some(_) => None,
+        None => {
+            let mut chars = flag.chars().skip(1);
+            match (chars.next(), chars.next()) {
+                (Some(short), None) => cli.get_arguments().find(|a| a.get_short() == Some(short)),
+                _ => None,
+            }
+        }
+    };
+    arg.is_some_and(|a| a.get_action().takes_values())
+}
+
+// `a.b.c` is three segments, unless the child is literally named `a.b.c`
+fn split_segment(node: &CmdNode, arg: &str) -> Vec<String> {
+    if arg.contains('.') && !node.children.iter().any(|c| c.name == arg) {
+        model::split_path(arg)
+    } else {
+        vec![arg.to_string()]
+    }
+}
+
+// The full name `seg` stands for among the children of `node`, if any
+fn resolve_segment(node: &CmdNode, path: &[String], seg: &str) -> anyhow::Result<Option<String>> {
+    let exact = node
+        .children
+        .iter()
+        .find(|c| c.name == seg || c.aliases.iter().any(|a| a == seg));
+    if let Some(child) = exact {
+        return Ok(Some(child.name.clone()));
+    }
+    if node.cmd.is_some() || seg.is_empty() {
+        return Ok(None);
+    }
+
+    let candidates: Vec<&CmdNode> = node
+        .sorted_children()
+        .into_iter()
+        .filter(|c| !(c.hidden || c.private))
+        .filter(|c| c.name.starts_with(seg) || c.aliases.iter().any(|a| a.starts_with(seg)))
+        .collect();
+    match candidates.as_slice() {
+        [] => Ok(None),
+        [one] => Ok(Some(one.name.clone())),
+        many => Err(YallaError::Ambiguous {
+            prefix: seg.to_string(),
+            candidates: many
+                .iter()
+                .map(|c| {
+                    let full = [path, std::slice::from_ref(&c.name)].concat();
+                    (format!("yalla {}", full.join(" ")), c.description.clone())
+                })
+                .collect(),
+        }
+        .into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root() -> CmdNode {
+        let task = |name: &str| CmdNode {
+            name: name.to_string(),
+            cmd: Some(format!("echo {name}")),
+            ..Default::default()
+        };
+        CmdNode {
+            name: "yalla".to_string(),
+            children: vec![
+                CmdNode {
+                    name: "kube".to_string(),
+                    children: vec![
+                        CmdNode {
+                            name: "dev".to_string(),
+                            children: vec![task("apply")],
+                            ..Default::default()
+                        },
+                        task("deploy"),
+                    ],
+                    ..Default::default()
+                },
+                CmdNode {
+                    name: "script".to_string(),
+                    cmd: Some("run.sh".to_string()),
+                    children: vec![task("test")],
+                    ..Default::default()
+                },
+                task("stop"),
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn expand(args: &[&str]) -> anyhow::Result<Vec<String>> {
+        let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        let cli = crate::clap_util::with_runner_flags(Command::new("yalla"));
+        expand_args(&root(), &cli, &args)
+    }
+
+    #[test]
+    fn unique_prefixes_and_dotted_paths_expand() {
+        assert_eq!(
+            expand(&["ku", "dev", "a"]).unwrap(),
+            ["kube", "dev", "apply"]
+        );
+        assert_eq!(
+            expand(&["-v", "kube.dev.a", "--", "x"]).unwrap(),
+            ["-v", "kube", "dev", "apply", "--", "x"]
+        );
+        assert_eq!(
+            expand(&["ku", "dev.apply"]).unwrap(),
+            ["kube", "dev", "apply"]
+        );
+    }
+
+    #[test]
+    fn values_of_runner_options_are_not_tasks() {
+        assert_eq!(
+            expand(&["--log-dir", "s", "--report", "ci.xml", "ku.dev.a"]).unwrap(),
+            [
+                "--log-dir",
+                "s",
+                "--report",
+                "ci.xml",
+                "kube",
+                "dev",
+                "apply"
+            ]
+        );
+        assert_eq!(
+            expand(&["--output=grouped", "-v", "st"]).unwrap(),
+            ["--output=grouped", "-v", "stop"]
+        );
+    }
+
+    #[test]
+    fn runnable_tasks_keep_their_arguments() {
+        assert_eq!(expand(&["script", "te"]).unwrap(), ["script", "te"]);
+        assert_eq!(expand(&["stop", "v1.2"]).unwrap(), ["stop", "v1.2"]);
+        assert_eq!(expand(&["script", "te.st"]).unwrap(), ["script", "te.st"]);
+        assert_eq!(expand(&[":list", "ku"]).unwrap(), [":list", "ku"]);
+    }
+
+    #[test]
+    fn mistyped_tail_of_a_dotted_path_is_an_error() {
+        let err = expand(&["script.tset"]).unwrap_err();
+        assert_eq!(err.to_string(), "No such task: script.tset");
+        let err = expand(&["kube.dev.aply", "x"]).unwrap_err();
+        assert_eq!(err.to_string(), "No such task: kube.dev.aply");
+    }
+
+    #[test]
+    fn alias_to_an_alias_is_an_error_not_a_panic() {
+        let alias = |name: &str, target: &str| CmdNode {
+            name: name.to_string(),
+            alias_of: Some(target.to_string()),
+            ..Default::default()
+        };
+        let mut root = root();
+        root.children.push(alias("k", "kube.dev"));
+        root.children.push(alias("kk", "k"));
+        let cli = crate::clap_util::with_runner_flags(Command::new("yalla"));
+        let err = expand_args(&root, &cli, &["kk".to_string()]).unwrap_err();
+        assert_eq!(err.to_string(), "No such task: kk");
+    }
+
+    #[test]
+    fn ambiguous_prefix_lists_candidates() {
+        let err = expand(&["kube", "d"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "'d' is ambiguous; it could be:\n  yalla kube dev\n  yalla kube deploy"
+        );
+        assert!(expand(&["s"]).is_err());
+    }
+}
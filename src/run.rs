@@ -0,0 +1,323 @@
+//! Running a resolved task: guards, confirmation, watch mode, caching, then the command
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use clap::ArgMatches;
+
+use crate::cache::{self, Freshness};
+use crate::error::YallaError;
+use crate::guard;
+use crate::history;
+use crate::model::{self, CmdNode, OnUnmet};
+use crate::output::Output;
+use crate::process;
+use crate::prompt;
+use crate::report::{self, Outcome, Status};
+use crate::watch;
+
+/// How to run a task; the CLI sets these from the options given ahead of the task path,
+/// e.g. `yalla --force ci test`
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct Options {
+    /// Run even when the outputs are up to date (`--force`)
+    pub force: bool,
+    /// Say why a task runs or is skipped (`--verbose`)
+    pub verbose: bool,
+    /// Rerun whenever the task's sources change (`--watch`)
+    pub watch: bool,
+    /// Answer yes to `confirm` prompts (`--yes`)
+    pub yes: bool,
+    /// Print a summary of what ran and how long it took (`--time`)
+    pub time: bool,
+    /// Write a JUnit XML report of the run to this file (`--report`)
+    pub report: Option<PathBuf>,
+    /// Copy the output of tasks without their own `log` to a file here (`--log-dir`)
+    pub log_dir: Option<PathBuf>,
+    /// Show the task's output as is, prefixed or grouped (`--output`)
+    pub output: Output,
+    /// Append the run to `.yalla/history.jsonl`, for `:history`; the CLI does unless
+    /// `YALLA_NO_HISTORY` is set
+    pub history: bool,
+}
+
+impl Options {
+    pub(crate) fn from_matches(m: &ArgMatches) -> Self {
+        Options {
+            force: m.get_flag("force"),
+            verbose: m.get_flag("verbose"),
+            watch: m.get_flag("watch"),
+            yes: m.get_flag("yes"),
+            time: m.get_flag("time"),
+            report: m.get_one::<PathBuf>("report").cloned(),
+            log_dir: m.get_one::<PathBuf>("log_dir").cloned(),
+            output: m
+                .get_one::<String>("output")
+                .map_or(Output::Raw, |o| Output::from_name(o)),
+            history: std::env::var_os("YALLA_NO_HISTORY").is_none_or(|v| v.is_empty()),
+        }
+    }
+}
+
+/// Run the task at `path` (dotted segments, aliases allowed) with `args` appended to its
+/// command, waiting for it to finish. Private tasks are refused unless this process was
+/// started by another task, as on the command line.
+///
+/// ```no_run
+/// let root = yalla::load_root("yalla", yalla::YALLAFILE)?;
+/// let path = yalla::split_path("ci.test");
+/// yalla::run_task(&root, &path, &["--quiet".to_string()], yalla::Options::default())?;
+/// # Ok::<(), yalla::YallaError>(())
+/// ```
+pub fn run_task(
+    root: &CmdNode,
+    path: &[String],
+    args: &[String],
+    opts: Options,
+) -> Result<(), YallaError> {
+    let unknown = || YallaError::UnknownTask {
+        path: path.join("."),
+    };
+    let path = model::canonical_path(root, path).ok_or_else(unknown)?;
+    let node = model::find_node(root, &path).ok_or_else(unknown)?;
+    refuse_private(root, &path)?;
+    if node.cmd.is_none() {
+        return Err(YallaError::NotRunnable {
+            path: path.join("."),
+        });
+    }
+    task(node, &path, args, opts)
+}
+
+/// Refuse the task at canonical `path` if it is private, unless another task is running it.
+pub(crate) fn refuse_private(root: &CmdNode, path: &[String]) -> Result<(), YallaError> {
+    if model::is_private(root, path) && !process::in_task() {
+        return Err(YallaError::Private {
+            path: path.join("."),
+        });
+    }
+    Ok(())
+}
+
+/// Run `node` (found at canonical `path`) with `args` appended to its command, then
+/// print the `--time` summary and write the `--report`.
+pub(crate) fn task(
+    node: &CmdNode,
+    path: &[String],
+    args: &[String],
+    opts: Options,
+) -> Result<(), YallaError> {
+    let started = Instant::now();
+    let result = attempt(node, path, args, &opts);
+    let time = opts.time || node.time;
+    if !time && opts.report.is_none() {
+        return result.map(|_| ());
+    }
+    let status = match &result {
+        Ok(Ran::Executed) => Status::Passed,
+        Ok(Ran::Cached(reason)) => Status::Cached(reason.clone()),
+        Ok(Ran::Skipped) => Status::Skipped,
+        Err(e) => Status::Failed(with_causes(e)),
+    };
+    let outcomes = [Outcome {
+        task: path.join("."),
+        status,
+        duration: started.elapsed(),
+    }];
+    if time {
+        eprint!("{}", report::summary(&outcomes));
+    }
+    if let Some(file) = &opts.report {
+        let written = report::write_junit(file, &outcomes);
+        // The task's own error matters more than a report that could not be written
+        if result.is_ok() {
+            written?;
+        } else if let Err(e) = written {
+            eprintln!("yalla: {}", with_causes(&e));
+        }
+    }
+    result.map(|_| ())
+}
+
+// `e` followed by what caused it, like anyhow's `{:#}`
+fn with_causes(e: &dyn std::error::Error) -> String {
+    let mut out = e.to_string();
+    let mut cause = e.source();
+    while let Some(c) = cause {
+        out.push_str(&format!(": {c}"));
+        cause = c.source();
+    }
+    out
+}
+
+/// What `attempt` did with a task that did not fail
+enum Ran {
+    Executed,
+    /// Up to date, for this reason
+    Cached(String),
+    /// Requirements unmet, with `on_unmet = "skip"`
+    Skipped,
+}
+
+fn attempt(
+    node: &CmdNode,
+    path: &[String],
+    args: &[String],
+    opts: &Options,
+) -> Result<Ran, YallaError> {
+    let Some(cmd) = &node.cmd else {
+        return Err(YallaError::NotRunnable {
+            path: path.join("."),
+        });
+    };
+    let key = path.join(".");
+    if opts.watch {
+        refuse_watch_conflicts(node, &key, opts)?;
+    }
+    let unmet = guard::unmet(node);
+    if !unmet.is_empty() {
+        return match node.on_unmet {
+            OnUnmet::Skip => {
+                for u in &unmet {
+                    eprintln!("yalla: {key}: {u}");
+                }
+                eprintln!("yalla: skipping {key}: requirements not met");
+                Ok(Ran::Skipped)
+            }
+            OnUnmet::Fail => Err(YallaError::Unmet { task: key, unmet }),
+        };
+    }
+
+    if let Some(question) = &node.confirm
+        && !opts.yes
+    {
+        // Fail closed: nobody can answer the prompt
+        if !prompt::interactive() {
+            return Err(YallaError::NeedsConfirmation { task: key });
+        }
+        let confirmed = prompt::confirm(question).map_err(|source| YallaError::Io {
+            context: format!("task '{key}': reading the answer to its confirmation prompt"),
+            source,
+        })?;
+        if !confirmed {
+            return Err(YallaError::Cancelled { task: key });
+        }
+    }
+
+    let cmd = process::with_args(cmd, args)?;
+    if opts.watch {
+        // Without explicit watch patterns, fall back to the task's sources
+        let patterns = if node.watch.is_empty() {
+            &node.sources
+        } else {
+            &node.watch
+        };
+        watch::run(
+            &key,
+            &cmd,
+            &node.stdin,
+            Path::new("."),
+            patterns,
+            watch::stop_on_signals(),
+        )
+        .map_err(|e| with_install_hint(e, node))?;
+        return Ok(Ran::Executed);
+    }
+
+    match cache::check(node, &key, &cmd, opts.force)? {
+        Freshness::Fresh(reason) => {
+            if opts.verbose {
+                eprintln!("yalla: skipping {key}: {reason}");
+            }
+            return Ok(Ran::Cached(reason));
+        }
+        Freshness::Stale(reason) => {
+            if opts.verbose {
+                eprintln!("yalla: running {key}: {reason}");
+            }
+        }
+    }
+    let started = SystemTime::now();
+    let log = log_path(node, &key, opts, started);
+    let timeout = node.timeout.map(Duration::from_secs);
+    let result = process::execute(
+        &key,
+        &cmd,
+        &node.stdin,
+        log.as_deref(),
+        opts.output,
+        timeout,
+    )
+    .map_err(|e| with_install_hint(e, node));
+    if let (Err(_), Some(log)) = (&result, &log) {
+        eprintln!("yalla: output of {key} saved to {}", log.display());
+    }
+    if opts.history {
+        let exit_code = result.as_ref().map_or_else(YallaError::exit_code, |_| 0);
+        let run = history::Run::finished(&key, args, &cmd, started, exit_code);
+        if let Err(e) = history::record(&run) {
+            eprintln!("yalla: not recorded in history: {e:#}");
+        }
+    }
+    result?;
+    // The task itself succeeded; it just runs again next time
+    if let Err(e) = cache::record(node, &key, &cmd) {
+        eprintln!("yalla: cache not updated for {key}: {e:#}");
+    }
+    Ok(Ran::Executed)
+}
+
+// Watch mode restarts the task with its output inherited, so it cannot log or reformat it
+fn refuse_watch_conflicts(node: &CmdNode, key: &str, opts: &Options) -> Result<(), YallaError> {
+    let option = if node.log.is_some() {
+        "its `log`"
+    } else if opts.log_dir.is_some() {
+        "--log-dir"
+    } else {
+        match opts.output {
+            Output::Raw => return Ok(()),
+            Output::Prefixed => "--output prefixed",
+            Output::Grouped => "--output grouped",
+        }
+    };
+    Err(YallaError::WatchConflict {
+        task: key.to_string(),
+        option: option.to_string(),
+    })
+}
+
+// Where to copy the task's output: its own `log`, or a file named after it in `--log-dir`
+fn log_path(node: &CmdNode, key: &str, opts: &Options, started: SystemTime) -> Option<PathBuf> {
+    // Basic ISO 8601 (20261018T093000Z), as colons are not allowed in Windows file names
+    let timestamp = history::timestamp(started).replace(['-', ':'], "");
+    let fill = |template: &str| {
+        template
+            .replace("{{task}}", key)
+            .replace("{{timestamp}}", &timestamp)
+    };
+    match (&node.log, &opts.log_dir) {
+        (Some(template), _) => Some(PathBuf::from(fill(template))),
+        (None, Some(dir)) => Some(dir.join(fill("{{task}}-{{timestamp}}.log"))),
+        (None, None) => None,
+    }
+}
+
+// A program that is not installed points at the task's `install_hint`
+fn with_install_hint(e: YallaError, node: &CmdNode) -> YallaError {
+    match e {
+        YallaError::Spawn {
+            task,
+            program,
+            source,
+            suggestions,
+            install_hint: None,
+        } => YallaError::Spawn {
+            task,
+            program,
+            source,
+            suggestions,
+            install_hint: node.install_hint.clone(),
+        },
+        e => e,
+    }
+}
@@ -0,0 +1,343 @@
+//! The keys a Yallafile understands, and the JSON Schema generated from them
+//!
+//! `schema/yallafile.schema.json` is generated from [`TASK_KEYS`]; a test keeps it in sync.
+use serde_json::{Map, Value as Json, json};
+use toml::Value;
+
+/// Version of the `:dump` output format; bump on incompatible changes
+pub const DUMP_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Kind {
+    String,
+    StringList,
+    Bool,
+    Integer,
+    /// A whole number of seconds, at least 1
+    Seconds,
+    Examples,
+    OneOf(&'static [&'static str]),
+    /// `"null"`, `"inherit"`, `{ file = "..." }` or `{ text = "..." }`
+    Stdin,
+}
+
+impl Kind {
+    /// Whether `v` is a valid value for a key of this kind
+    pub fn accepts(self, v: &Value) -> bool {
+        let strings = |v: &Value| v.as_array().is_some_and(|a| a.iter().all(Value::is_str));
+        match self {
+            Kind::String => v.is_str(),
+            Kind::StringList => strings(v),
+            Kind::Bool => v.is_bool(),
+            Kind::Integer => v.is_integer(),
+            Kind::Seconds => v.as_integer().is_some_and(|n| n > 0),
+            Kind::Examples => v.as_array().is_some_and(|a| {
+                a.iter().all(|ex| match ex {
+                    Value::String(_) => true,
+                    Value::Table(t) => t.get("cmd").is_some_and(Value::is_str),
+                    _ => false,
+                })
+            }),
+            Kind::OneOf(values) => v.as_str().is_some_and(|s| values.contains(&s)),
+            Kind::Stdin => match v {
+                Value::String(s) => s == "null" || s == "inherit",
+                Value::Table(t) => {
+                    t.len() == 1
+                        && ["file", "text"]
+                            .iter()
+                            .any(|k| t.get(*k).is_some_and(Value::is_str))
+                }
+                _ => false,
+            },
+        }
+    }
+
+    /// What a value of this kind looks like, for error messages
+    pub fn expected(self) -> String {
+        match self {
+            Kind::String => "a string".to_string(),
+            Kind::StringList => "a list of strings".to_string(),
+            Kind::Bool => "true or false".to_string(),
+            Kind::Integer => "an integer".to_string(),
+            Kind::Seconds => "a positive number of seconds".to_string(),
+            Kind::Examples => {
+                "a list of strings or `{ cmd = \"...\", desc = \"...\" }` tables".to_string()
+            }
+            Kind::OneOf(values) => {
+                let quoted: Vec<String> = values.iter().map(|v| format!("\"{v}\"")).collect();
+                format!("one of {}", quoted.join(", "))
+            }
+            Kind::Stdin => {
+                "\"null\", \"inherit\", `{ file = \"...\" }` or `{ text = \"...\" }`".to_string()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Key {
+    pub name: &'static str,
+    pub kind: Kind,
+    pub doc: &'static str,
+}
+
+/// Keys allowed in any task table; every other table-valued key is a child task
+pub const TASK_KEYS: &[Key] = &[
+    Key {
+        name: "description",
+        kind: Kind::String,
+        doc: "One-line summary shown in help and listings",
+    },
+    Key {
+        name: "long_description",
+        kind: Kind::String,
+        doc: "Extended help shown by `--help`",
+    },
+    Key {
+        name: "cmd",
+        kind: Kind::String,
+        doc: "Command to run, split like a POSIX shell would (no shell is involved)",
+    },
+    Key {
+        name: "sources",
+        kind: Kind::StringList,
+        doc: "Glob patterns of inputs; the task is skipped when they are unchanged",
+    },
+    Key {
+        name: "outputs",
+        kind: Kind::StringList,
+        doc: "Glob patterns of outputs; the task is skipped when they are newer than its sources",
+    },
+    Key {
+        name: "watch",
+        kind: Kind::StringList,
+        doc: "Glob patterns that `--watch` reruns the task on, instead of its sources",
+    },
+    Key {
+        name: "log",
+        kind: Kind::String,
+        doc: "File to copy the task's output to; {{task}} and {{timestamp}} are filled in",
+    },
+    Key {
+        name: "time",
+        kind: Kind::Bool,
+        doc: "Print how long the task took, as `--time` does; at the top level, for every task",
+    },
+    Key {
+        name: "stdin",
+        kind: Kind::Stdin,
+        doc: "What the task reads on stdin: \"inherit\" (default), \"null\", a file or a text",
+    },
+    Key {
+        name: "requires",
+        kind: Kind::StringList,
+        doc: "Programs that must be on PATH",
+    },
+    Key {
+        name: "requires_env",
+        kind: Kind::StringList,
+        doc: "Environment variables that must be set",
+    },
+    Key {
+        name: "if",
+        kind: Kind::String,
+        doc: "Command that must succeed for the task to run",
+    },
+    Key {
+        name: "platforms",
+        kind: Kind::StringList,
+        doc: "Operating systems (or `unix`/`windows`) the task runs on",
+    },
+    Key {
+        name: "on_unmet",
+        kind: Kind::OneOf(&["fail", "skip"]),
+        doc: "Whether unmet requirements fail the run or skip the task",
+    },
+    Key {
+        name: "install_hint",
+        kind: Kind::String,
+        doc: "How to install the task's program, shown when it is not found",
+    },
+    Key {
+        name: "confirm",
+        kind: Kind::String,
+        doc: "Question to confirm before running; needs --yes when not interactive",
+    },
+    Key {
+        name: "timeout",
+        kind: Kind::Seconds,
+        doc: "Seconds the task may run before it is killed and fails",
+    },
+    Key {
+        name: "group",
+        kind: Kind::String,
+        doc: "Heading the task is listed under in its parent's help",
+    },
+    Key {
+        name: "order",
+        kind: Kind::Integer,
+        doc: "Position among its siblings; lower comes first, tasks without one come last",
+    },
+    Key {
+        name: "aliases",
+        kind: Kind::StringList,
+        doc: "Alternative names for this task",
+    },
+    Key {
+        name: "hidden",
+        kind: Kind::Bool,
+        doc: "Leave the task out of help (also implied by a leading underscore)",
+    },
+    Key {
+        name: "private",
+        kind: Kind::Bool,
+        doc: "Leave the task out of help and only let other tasks run it",
+    },
+    Key {
+        name: "choices",
+        kind: Kind::StringList,
+        doc: "Values offered when completing the task's arguments in the shell",
+    },
+    Key {
+        name: "examples",
+        kind: Kind::Examples,
+        doc: "Example invocations shown at the end of the task's help",
+    },
+    Key {
+        name: "see_also",
+        kind: Kind::StringList,
+        doc: "Related tasks (dotted paths) or references shown at the end of the task's help",
+    },
+];
+
+/// Keys allowed only at the top of the file, besides the `[aliases]` table
+pub const ROOT_KEYS: &[Key] = &[
+    Key {
+        name: "title",
+        kind: Kind::String,
+        doc: "Shown at the top of `yalla --help` unless `description` is set",
+    },
+    Key {
+        name: "preserve_order",
+        kind: Kind::Bool,
+        doc: "List tasks in the order they are declared instead of by name",
+    },
+];
+
+/// JSON Schema for a value of the given kind
+fn key_schema(kind: Kind) -> Json {
+    match kind {
+        Kind::String => json!({ "type": "string" }),
+        Kind::StringList => json!({ "type": "array", "items": { "type": "string" } }),
+        Kind::Bool => json!({ "type": "boolean" }),
+        Kind::Integer => json!({ "type": "integer" }),
+        Kind::Seconds => json!({ "type": "integer", "minimum": 1 }),
+        Kind::Examples => json!({
+            "type": "array",
+            "items": {
+                "anyOf": [
+                    { "type": "string" },
+                    {
+                        "type": "object",
+                        "properties": {
+                            "cmd": { "type": "string" },
+                            "desc": { "type": "string" }
+                        },
+                        "required": ["cmd"],
+                        "additionalProperties": false
+                    }
+                ]
+            }
+        }),
+        Kind::OneOf(values) => json!({ "enum": values }),
+        Kind::Stdin => json!({
+            "anyOf": [
+                { "enum": ["null", "inherit"] },
+                {
+                    "type": "object",
+                    "properties": { "file": { "type": "string" } },
+                    "required": ["file"],
+                    "additionalProperties": false
+                },
+                {
+                    "type": "object",
+                    "properties": { "text": { "type": "string" } },
+                    "required": ["text"],
+                    "additionalProperties": false
+                }
+            ]
+        }),
+    }
+}
+
+/// The child task that `key = value` defines in `parent`: any table, except the value of a
+/// table-valued key like `stdin`. Those only count as values in tables with a `cmd`, since
+/// only a command has a stdin; in a namespace, `[ci.stdin]` is a task like any other.
+pub fn child_task<'a>(
+    parent: &toml::Table,
+    key: &str,
+    value: &'a Value,
+) -> Option<&'a toml::Table> {
+    let keyed = parent.contains_key("cmd")
+        && TASK_KEYS
+            .iter()
+            .any(|k| k.name == key && matches!(k.kind, Kind::Stdin));
+    value.as_table().filter(|_| !keyed)
+}
+
+/// JSON Schema (draft 2020-12) describing the Yallafile format.
+pub fn yallafile_schema() -> Json {
+    let mut task_props = Map::new();
+    for key in TASK_KEYS {
+        let mut prop = key_schema(key.kind);
+        prop["description"] = json!(key.doc);
+        task_props.insert(key.name.to_string(), prop);
+    }
+
+    let mut root_props = task_props.clone();
+    for key in ROOT_KEYS {
+        let mut prop = key_schema(key.kind);
+        prop["description"] = json!(key.doc);
+        root_props.insert(key.name.to_string(), prop);
+    }
+    root_props.insert(
+        "aliases".to_string(),
+        json!({
+            "type": "object",
+            "description": "Top-level shortcuts mapping a name to a dotted task path",
+            "additionalProperties": { "type": "string" }
+        }),
+    );
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "Yallafile",
+        "description": "Task definitions for the yalla task runner",
+        "type": "object",
+        "properties": root_props,
+        "additionalProperties": { "$ref": "#/$defs/task" },
+        "$defs": {
+            "task": {
+                "type": "object",
+                "properties": task_props,
+                "additionalProperties": { "$ref": "#/$defs/task" }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn committed_schema_is_up_to_date() {
+        let mut generated = serde_json::to_string_pretty(&yallafile_schema()).unwrap();
+        generated.push('\n');
+        let committed = std::fs::read_to_string("schema/yallafile.schema.json").unwrap();
+        assert!(
+            committed == generated,
+            "schema/yallafile.schema.json is stale; regenerate it with `yalla :schema`"
+        );
+    }
+}
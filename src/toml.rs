@@ -1,27 +1,106 @@
+use std::collections::HashMap;
 use std::fs;
+use std::io;
+use std::ops::Range;
 
-use anyhow::{Context, Error};
 use toml::Table;
+use toml::de::{DeTable, DeValue};
 
-use crate::model::CmdNode;
+use crate::builtin;
+use crate::diagnostic::Diagnostic;
+use crate::error::YallaError;
+use crate::model::{self, CmdNode, Example, Location, OnUnmet, Stdin};
+use crate::schema::{self, Key, Kind, ROOT_KEYS, TASK_KEYS};
 
-pub fn table_to_root(root_name: &str, t: &Table) -> CmdNode {
-    fn build(name: &str, tbl: &Table) -> CmdNode {
+/// The file tasks are loaded from, relative to the working directory
+pub const YALLAFILE: &str = "Yallafile";
+
+// Top-level table holding `alias = "dotted.path"` shortcuts rather than tasks
+pub const ALIASES_TABLE: &str = "aliases";
+// Top-level flag listing tasks in declaration order instead of by name
+pub const PRESERVE_ORDER: &str = "preserve_order";
+
+/// Build the task tree from a parsed Yallafile, named `root_name` (the program's name).
+///
+/// Problems are [`YallaError::Invalid`], naming the key they are about but not yet
+/// located in a file; [`load_root`] and [`parse_root`] add the location.
+pub fn table_to_root(root_name: &str, t: &Table) -> Result<CmdNode, YallaError> {
+    tree(root_name, t).map_err(YallaError::Invalid)
+}
+
+fn tree(root_name: &str, t: &Table) -> Result<CmdNode, Diagnostic> {
+    check_types(t, &mut Vec::new())?;
+
+    fn build(name: &str, tbl: &Table, top: bool) -> CmdNode {
         let description = tbl
             .get("description")
             .and_then(|v| v.as_str())
             .map(|s| s.to_string());
 
+        let long_description = tbl
+            .get("long_description")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+
         let cmd = tbl
             .get("cmd")
             .and_then(|v| v.as_str())
             .map(|s| s.to_string());
 
+        let sources = str_list(tbl, "sources");
+        let outputs = str_list(tbl, "outputs");
+        let watch = str_list(tbl, "watch");
+        let log = tbl
+            .get("log")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+        let stdin = stdin(tbl);
+        let time = tbl.get("time").and_then(|v| v.as_bool()).unwrap_or(false);
+
+        let requires = str_list(tbl, "requires");
+        let requires_env = str_list(tbl, "requires_env");
+        let platforms = str_list(tbl, "platforms");
+        let condition = tbl
+            .get("if")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+        let install_hint = tbl
+            .get("install_hint")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+        let confirm = tbl
+            .get("confirm")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+        let timeout = tbl
+            .get("timeout")
+            .and_then(|v| v.as_integer())
+            .and_then(|n| u64::try_from(n).ok());
+        let aliases = str_list(tbl, "aliases");
+        let group = tbl
+            .get("group")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+        let order = tbl.get("order").and_then(|v| v.as_integer());
+        let hidden =
+            name.starts_with('_') || tbl.get("hidden").and_then(|v| v.as_bool()).unwrap_or(false);
+        let private = tbl
+            .get("private")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let on_unmet = match tbl.get("on_unmet").and_then(|v| v.as_str()) {
+            Some("skip") => OnUnmet::Skip,
+            _ => OnUnmet::Fail,
+        };
+
         // Collect child tables into a Vec for the new representation
         let mut children: Vec<CmdNode> = Vec::new();
         for (k, v) in tbl.iter() {
-            if let Some(child_tbl) = v.as_table() {
-                let child = build(k, child_tbl);
+            if top && k == ALIASES_TABLE {
+                continue;
+            }
+            if let Some(child_tbl) = schema::child_task(tbl, k, v) {
+                let child = build(k, child_tbl, false);
                 children.push(child);
             }
         }
@@ -29,36 +108,378 @@ pub fn table_to_root(root_name: &str, t: &Table) -> CmdNode {
         CmdNode {
             name: name.to_string(),
             description,
+            long_description,
             cmd,
             children,
+            sources,
+            outputs,
+            watch,
+            log,
+            stdin,
+            time,
+            requires,
+            requires_env,
+            condition,
+            platforms,
+            on_unmet,
+            install_hint,
+            confirm,
+            timeout,
+            group,
+            order,
+            aliases,
+            alias_of: None,
+            hidden,
+            private,
+            choices: str_list(tbl, "choices"),
+            examples: examples(tbl),
+            see_also: str_list(tbl, "see_also"),
+            source: None,
         }
     }
 
-    let mut root = build(root_name, t);
+    let mut root = build(root_name, t, true);
     if root.description.is_none()
         && let Some(title) = t.get("title").and_then(|v| v.as_str())
     {
         root.description = Some(title.to_string());
     }
-    root
+
+    if let Some(aliases) = t.get(ALIASES_TABLE).and_then(|v| v.as_table()) {
+        for (alias, target) in aliases {
+            let key = vec![ALIASES_TABLE.to_string(), alias.clone()];
+            let target = target.as_str().ok_or_else(|| {
+                Diagnostic::new(format!(
+                    "Alias '{alias}' must map to a dotted task path, found {}",
+                    target.type_str()
+                ))
+                .at_key(key.clone())
+                .with_hint(format!("{alias} = \"ci.test\""))
+            })?;
+            let path = model::split_path(target);
+            // Aliases are resolved once, so a chain of them leads nowhere
+            if path
+                .first()
+                .is_some_and(|first| aliases.contains_key(first))
+            {
+                return Err(Diagnostic::new(format!(
+                    "Alias '{alias}' points to '{target}', which is itself an alias"
+                ))
+                .at_key(key)
+                .with_hint("point it at the task itself"));
+            }
+            if model::find_node(&root, &path).is_none() {
+                return Err(Diagnostic::new(format!(
+                    "Alias '{alias}' points to unknown task '{target}'"
+                ))
+                .at_key(key)
+                .with_hint("use the dotted path of a task defined in this file, e.g. `ci.test`"));
+            }
+            root.children.push(CmdNode {
+                name: alias.clone(),
+                description: Some(format!("Alias for '{}'", path.join(" "))),
+                alias_of: Some(path.join(".")),
+                ..Default::default()
+            });
+        }
+    }
+
+    // Tables keep declaration order; list tasks by name unless the file asks otherwise
+    if !t
+        .get(PRESERVE_ORDER)
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+    {
+        sort_by_name(&mut root);
+    }
+
+    check_name_collisions(&root, &[])?;
+    Ok(root)
+}
+
+fn sort_by_name(node: &mut CmdNode) {
+    node.children.sort_by(|a, b| a.name.cmp(&b.name));
+    for ch in &mut node.children {
+        sort_by_name(ch);
+    }
+}
+
+// Values of known keys must have the right type; unknown keys are left to `:check`
+fn check_types(tbl: &Table, path: &mut Vec<String>) -> Result<(), Diagnostic> {
+    let top = path.is_empty();
+    for (k, v) in tbl {
+        path.push(k.clone());
+        if top && k == ALIASES_TABLE {
+            if !v.is_table() {
+                return Err(Diagnostic::new(format!(
+                    "`{k}` must be a table, found {}",
+                    v.type_str()
+                ))
+                .at_key(path.clone())
+                .with_hint("[aliases]\nt = \"ci.test\""));
+            }
+        } else if let Some(child) = schema::child_task(tbl, k, v) {
+            check_types(child, path)?;
+        } else if let Some(key) = TASK_KEYS
+            .iter()
+            .chain(if top { ROOT_KEYS } else { &[] })
+            .find(|key| key.name == k)
+            && !key.kind.accepts(v)
+        {
+            let d = Diagnostic::new(format!(
+                "`{k}` must be {}, found {}",
+                key.kind.expected(),
+                v.type_str()
+            ))
+            .at_key(path.clone());
+            return Err(match type_hint(key) {
+                Some(hint) => d.with_hint(hint),
+                None => d,
+            });
+        }
+        path.pop();
+    }
+    Ok(())
+}
+
+fn type_hint(key: &Key) -> Option<String> {
+    let k = key.name;
+    match key.kind {
+        Kind::String => Some(format!("write it in quotes, e.g. {k} = \"...\"")),
+        Kind::StringList => Some(format!(
+            "use a list, even for one entry, e.g. {k} = [\"...\"]"
+        )),
+        Kind::Bool => Some(format!("{k} = true")),
+        Kind::Integer => Some(format!("{k} = 10")),
+        Kind::Seconds => Some(format!("{k} = 60")),
+        Kind::Examples => Some(format!("{k} = [{{ cmd = \"yalla ...\", desc = \"...\" }}]")),
+        Kind::OneOf(_) => None,
+        Kind::Stdin => Some(format!("{k} = \"null\", or {k} = {{ file = \"...\" }}")),
+    }
+}
+
+// Names and aliases must be unique among siblings for clap to route them
+fn check_name_collisions(node: &CmdNode, path: &[&str]) -> Result<(), Diagnostic> {
+    let mut seen: HashMap<&str, String> = HashMap::new();
+    for ch in &node.children {
+        let owner = match &ch.alias_of {
+            Some(target) => format!("the [aliases] entry for '{target}'"),
+            None => format!("'{}'", ch.name),
+        };
+        // Where the name is written: its table, its `aliases` list, or the `[aliases]` table
+        let key_of = |name: &String| -> Vec<String> {
+            let mut key: Vec<String> = path.iter().map(|s| s.to_string()).collect();
+            if ch.alias_of.is_some() {
+                key = vec![ALIASES_TABLE.to_string(), ch.name.clone()];
+            } else {
+                key.push(ch.name.clone());
+                if name != &ch.name {
+                    key.push("aliases".to_string());
+                }
+            }
+            key
+        };
+        for name in std::iter::once(&ch.name).chain(&ch.aliases) {
+            if builtin::is_builtin(name) {
+                return Err(Diagnostic::new(format!(
+                    "Name '{name}' is reserved for yalla's built-in commands"
+                ))
+                .at_key(key_of(name))
+                .with_hint(format!(
+                    "names starting with '{}' belong to yalla; rename the task",
+                    builtin::PREFIX
+                )));
+            }
+            if let Some(prev) = seen.insert(name, owner.clone()) {
+                let scope = if path.is_empty() {
+                    "the top level".to_string()
+                } else {
+                    format!("'{}'", path.join("."))
+                };
+                return Err(Diagnostic::new(format!(
+                    "Name '{name}' in {scope} is used by both {prev} and {owner}"
+                ))
+                .at_key(key_of(name))
+                .with_hint("rename one of them or remove the duplicate alias"));
+            }
+        }
+        let mut child_path = path.to_vec();
+        child_path.push(&ch.name);
+        check_name_collisions(ch, &child_path)?;
+    }
+    Ok(())
+}
+
+fn str_list(tbl: &Table, key: &str) -> Vec<String> {
+    tbl.get(key)
+        .and_then(|v| v.as_array())
+        .map(|a| {
+            a.iter()
+                .filter_map(|v| v.as_str())
+                .map(|s| s.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// `examples = [{ cmd = "...", desc = "..." }]`; a bare string is an example without a description
+fn examples(tbl: &Table) -> Vec<Example> {
+    let Some(list) = tbl.get("examples").and_then(|v| v.as_array()) else {
+        return Vec::new();
+    };
+    list.iter()
+        .filter_map(|v| match v {
+            toml::Value::String(cmd) => Some(Example {
+                cmd: cmd.clone(),
+                desc: None,
+            }),
+            toml::Value::Table(t) => Some(Example {
+                cmd: t.get("cmd")?.as_str()?.to_string(),
+                desc: t
+                    .get("desc")
+                    .and_then(|d| d.as_str())
+                    .map(|d| d.to_string()),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+fn stdin(tbl: &Table) -> Stdin {
+    match tbl.get("stdin") {
+        Some(toml::Value::String(s)) if s == "null" => Stdin::Null,
+        Some(toml::Value::Table(t)) => match (t.get("file"), t.get("text")) {
+            (Some(toml::Value::String(file)), _) => Stdin::File(file.clone()),
+            (_, Some(toml::Value::String(text))) => Stdin::Text(text.clone()),
+            _ => Stdin::Inherit,
+        },
+        _ => Stdin::Inherit,
+    }
 }
 
-pub fn load_toml_table(path: &str) -> Result<Table, Error> {
-    let input = fs::read_to_string(path).context(format!("reading {}", path))?;
-    let table: Table = toml::from_str(&input).context(format!("parsing {}", path))?;
-    Ok(table)
+/// Load a Yallafile into a task tree, recording where each node is defined.
+pub fn load_root(root_name: &str, path: &str) -> Result<CmdNode, YallaError> {
+    let input = fs::read_to_string(path).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => YallaError::NotFound {
+            path: path.to_string(),
+        },
+        _ => YallaError::Read {
+            path: path.to_string(),
+            source: e,
+        },
+    })?;
+    parse_root(root_name, path, &input)
+}
+
+/// Like [`load_root`], for Yallafile contents already in memory; `file` names them in
+/// errors and node locations.
+pub fn parse_root(root_name: &str, file: &str, input: &str) -> Result<CmdNode, YallaError> {
+    let table: Table = toml::from_str(input).map_err(|e| {
+        let d = Diagnostic::new(e.message().trim_end());
+        YallaError::Parse(match e.span() {
+            Some(span) => d.locate(file, input, span),
+            None => d,
+        })
+    })?;
+    build_root(root_name, file, input, &table)
+}
+
+// `table_to_root` for `table`, parsed from `input`, with errors and nodes located in `file`
+pub(crate) fn build_root(
+    root_name: &str,
+    file: &str,
+    input: &str,
+    table: &Table,
+) -> Result<CmdNode, YallaError> {
+    let spans = key_spans(input);
+    let mut root = tree(root_name, table).map_err(|d| {
+        YallaError::Invalid(match spans.get(&d.key).cloned() {
+            Some(span) => d.locate(file, input, span),
+            None => d,
+        })
+    })?;
+
+    let lines = spans
+        .into_iter()
+        .map(|(k, span)| (k, line_of(input, span.start)))
+        .collect();
+    annotate(&mut root, &mut Vec::new(), file, &lines);
+    Ok(root)
+}
+
+/// Line where each key path (e.g. `["tools", "lint", "cmd"]`) first appears in `input`.
+pub fn key_lines(input: &str) -> HashMap<Vec<String>, usize> {
+    key_spans(input)
+        .into_iter()
+        .map(|(k, span)| (k, line_of(input, span.start)))
+        .collect()
+}
+
+// Where each key path first appears: tables at their key, other keys at their value
+fn key_spans(input: &str) -> HashMap<Vec<String>, Range<usize>> {
+    fn walk(tbl: &DeTable, prefix: &mut Vec<String>, out: &mut HashMap<Vec<String>, Range<usize>>) {
+        for (k, v) in tbl.iter() {
+            prefix.push(k.get_ref().to_string());
+            let span = match v.get_ref() {
+                DeValue::Table(_) => k.span(),
+                _ => v.span(),
+            };
+            out.entry(prefix.clone()).or_insert(span);
+            if let DeValue::Table(t) = v.get_ref() {
+                walk(t, prefix, out);
+            }
+            prefix.pop();
+        }
+    }
+    let mut spans = HashMap::new();
+    if let Ok(doc) = DeTable::parse(input) {
+        walk(doc.get_ref(), &mut Vec::new(), &mut spans);
+    }
+    spans
+}
+
+fn annotate(
+    node: &mut CmdNode,
+    path: &mut Vec<String>,
+    file: &str,
+    lines: &HashMap<Vec<String>, usize>,
+) {
+    // `[aliases]` entries live in their own table
+    let key = match node.alias_of {
+        Some(_) => vec![ALIASES_TABLE.to_string(), node.name.clone()],
+        None => path.clone(),
+    };
+    node.source = Some(Location {
+        file: file.to_string(),
+        line: lines.get(&key).copied().unwrap_or(1),
+    });
+    for ch in &mut node.children {
+        path.push(ch.name.clone());
+        annotate(ch, path, file, lines);
+        path.pop();
+    }
+}
+
+fn line_of(input: &str, offset: usize) -> usize {
+    input[..offset].matches('\n').count() + 1
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::model::CmdNode;
-    use crate::toml::{load_toml_table, table_to_root};
+    use toml::Table;
+
+    use crate::error::YallaError;
+    use crate::model::{CmdNode, Example, Location};
+    use crate::toml::{load_root, table_to_root};
+
+    const BASIC: &str = "tests/fixtures/basic/Yallafile";
 
     #[test]
     fn table_to_root_builds_expected_tree() {
-        let table = load_toml_table("tests/fixtures/basic/Yallafile").expect("read fixture");
+        let input = std::fs::read_to_string(BASIC).expect("read fixture");
+        let table: Table = toml::from_str(&input).expect("parse fixture");
 
-        let got = table_to_root("yalla", &table);
+        let got = table_to_root("yalla", &table).expect("valid tree");
 
         let expect = CmdNode {
             name: "yalla".to_string(),
@@ -75,14 +496,17 @@ mod tests {
                             description: None,
                             cmd: Some("cargo build --release".to_string()),
                             children: vec![],
+                            ..Default::default()
                         },
                         CmdNode {
                             name: "test".to_string(),
                             description: None,
                             cmd: Some("cargo test --workspace".to_string()),
                             children: vec![],
+                            ..Default::default()
                         },
                     ],
+                    ..Default::default()
                 },
                 CmdNode {
                     name: "db".to_string(),
@@ -93,7 +517,9 @@ mod tests {
                         description: None,
                         cmd: Some("diesel migration run".to_string()),
                         children: vec![],
+                        ..Default::default()
                     }],
+                    ..Default::default()
                 },
                 CmdNode {
                     name: "git".to_string(),
@@ -104,7 +530,9 @@ mod tests {
                         description: None,
                         cmd: Some("git rev-parse --is-inside-work-tree".to_string()),
                         children: vec![],
+                        ..Default::default()
                     }],
+                    ..Default::default()
                 },
                 CmdNode {
                     name: "kube".to_string(),
@@ -119,8 +547,11 @@ mod tests {
                             description: None,
                             cmd: Some("kubectl apply -k k8s/overlays/dev".to_string()),
                             children: vec![],
+                            ..Default::default()
                         }],
+                        ..Default::default()
                     }],
+                    ..Default::default()
                 },
                 CmdNode {
                     name: "script".to_string(),
@@ -132,20 +563,24 @@ mod tests {
                             description: None,
                             cmd: Some("bash ./scripts/deploy.sh --env=prod".to_string()),
                             children: vec![],
+                            ..Default::default()
                         },
                         CmdNode {
                             name: "test".to_string(),
                             description: None,
                             cmd: Some("cargo test --all --all-features".to_string()),
                             children: vec![],
+                            ..Default::default()
                         },
                     ],
+                    ..Default::default()
                 },
                 CmdNode {
                     name: "stop".to_string(),
                     description: Some("Stop local services".to_string()),
                     cmd: Some("docker compose down".to_string()),
                     children: vec![],
+                    ..Default::default()
                 },
                 CmdNode {
                     name: "tools".to_string(),
@@ -157,6 +592,7 @@ mod tests {
                             description: None,
                             cmd: Some("cargo fmt".to_string()),
                             children: vec![],
+                            ..Default::default()
                         },
                         CmdNode {
                             name: "lint".to_string(),
@@ -166,18 +602,174 @@ mod tests {
                                     .to_string(),
                             ),
                             children: vec![],
+                            ..Default::default()
                         },
                         CmdNode {
                             name: "ls".to_string(),
                             description: None,
                             cmd: Some("ls -1".to_string()),
                             children: vec![],
+                            ..Default::default()
                         },
                     ],
+                    ..Default::default()
                 },
             ],
+            ..Default::default()
         };
 
         assert_eq!(got, expect);
     }
+
+    fn parse(src: &str) -> Result<CmdNode, YallaError> {
+        table_to_root("yalla", &toml::from_str(src).unwrap())
+    }
+
+    #[test]
+    fn aliases_resolve_to_their_targets() {
+        let root = parse(
+            r#"
+            [aliases]
+            t = "ci.test"
+
+            [ci.test]
+            cmd = "cargo test"
+            aliases = ["tst"]
+            "#,
+        )
+        .unwrap();
+
+        let t = root.children.iter().find(|c| c.name == "t").unwrap();
+        assert_eq!(t.alias_of.as_deref(), Some("ci.test"));
+        assert!(!root.children.iter().any(|c| c.name == "aliases"));
+        assert_eq!(
+            root.children
+                .iter()
+                .find(|c| c.name == "ci")
+                .unwrap()
+                .children[0]
+                .aliases,
+            vec!["tst".to_string()]
+        );
+    }
+
+    #[test]
+    fn alias_collisions_are_rejected() {
+        let err = parse(
+            r#"
+            [tools.lint]
+            cmd = "cargo clippy"
+            aliases = ["l"]
+
+            [tools.ls]
+            cmd = "ls"
+            aliases = ["l"]
+            "#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Name 'l' in 'tools' is used by both 'lint' and 'ls'"
+        );
+
+        let err = parse(
+            r#"
+            [aliases]
+            stop = "ci.test"
+
+            [stop]
+            cmd = "docker compose down"
+
+            [ci.test]
+            cmd = "cargo test"
+            "#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Name 'stop' in the top level is used by both 'stop' and the [aliases] entry for 'ci.test'"
+        );
+    }
+
+    #[test]
+    fn alias_to_unknown_task_is_rejected() {
+        let err = parse("[aliases]\nt = \"ci.nope\"\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Alias 't' points to unknown task 'ci.nope'"
+        );
+    }
+
+    #[test]
+    fn alias_to_alias_is_rejected() {
+        let input = "[aliases]\nk = \"kube.dev\"\nkk = \"k\"\n[kube.dev]\ncmd = \"kubectl\"\n";
+        let err = parse(input).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Alias 'kk' points to 'k', which is itself an alias"
+        );
+    }
+
+    #[test]
+    fn builtin_prefix_is_reserved() {
+        let err = parse("[\":list\"]\ncmd = \"ls\"\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Name ':list' is reserved for yalla's built-in commands"
+        );
+    }
+
+    #[test]
+    fn children_sorted_by_name_unless_order_preserved() {
+        let names = |root: &CmdNode| -> Vec<String> {
+            root.sorted_children()
+                .iter()
+                .map(|c| c.name.clone())
+                .collect()
+        };
+        let doc = "[test]\ncmd = \"t\"\n[build]\ncmd = \"b\"\n[lint]\ncmd = \"l\"\norder = 1\n";
+        assert_eq!(names(&parse(doc).unwrap()), ["lint", "build", "test"]);
+
+        let doc = format!("preserve_order = true\n{doc}");
+        assert_eq!(names(&parse(&doc).unwrap()), ["lint", "test", "build"]);
+    }
+
+    #[test]
+    fn examples_accept_tables_and_strings() {
+        let root = parse(
+            "[migrate]\ncmd = \"diesel migration run\"\n\
+             examples = [{ cmd = \"yalla migrate\", desc = \"Apply\" }, \"yalla migrate -- -v\"]\n",
+        )
+        .unwrap();
+        assert_eq!(
+            root.children[0].examples,
+            vec![
+                Example {
+                    cmd: "yalla migrate".to_string(),
+                    desc: Some("Apply".to_string()),
+                },
+                Example {
+                    cmd: "yalla migrate -- -v".to_string(),
+                    desc: None,
+                },
+            ]
+        );
+        // Examples are a list, not a child task
+        assert_eq!(root.children[0].children, vec![]);
+    }
+
+    #[test]
+    fn load_root_records_source_lines() {
+        let root = load_root("yalla", BASIC).unwrap();
+        let line = |path: &[&str]| {
+            let path: Vec<String> = path.iter().map(|s| s.to_string()).collect();
+            crate::model::find_node(&root, &path)
+                .and_then(|n| n.source.clone())
+                .map(|Location { file, line }| (file, line))
+        };
+        assert_eq!(line(&["stop"]), Some((BASIC.to_string(), 4)));
+        assert_eq!(line(&["tools", "lint"]), Some((BASIC.to_string(), 15)));
+        // Implicit parents point at the first header mentioning them
+        assert_eq!(line(&["kube", "dev"]), Some((BASIC.to_string(), 44)));
+    }
 }
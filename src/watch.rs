@@ -0,0 +1,307 @@
+//! Watch mode: rerun a task whenever matching files change
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use glob::Pattern;
+use ignore::gitignore::Gitignore;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+
+use crate::error::YallaError;
+use crate::model::Stdin;
+use crate::process;
+
+/// Set once SIGINT, SIGTERM or SIGHUP arrives after [`stop_on_signals`]
+static SIGNALLED: AtomicBool = AtomicBool::new(false);
+
+/// Quiet period after the last change before the task is restarted
+const DEBOUNCE: Duration = Duration::from_millis(200);
+/// How often the loop checks for a finished child or a stop request
+const POLL: Duration = Duration::from_millis(100);
+
+/// A flag for [`run`] that is set when yalla is interrupted or terminated.
+///
+/// The task runs in a process group of its own, which Ctrl-C in the terminal does not
+/// reach, so yalla catches the signal and stops the task itself.
+pub fn stop_on_signals() -> &'static AtomicBool {
+    #[cfg(unix)]
+    {
+        extern "C" fn handle(_: libc::c_int) {
+            SIGNALLED.store(true, Ordering::SeqCst);
+        }
+        for signal in [libc::SIGINT, libc::SIGTERM, libc::SIGHUP] {
+            // SAFETY: the handler only stores to an atomic, which is async-signal-safe
+            unsafe {
+                libc::signal(
+                    signal,
+                    handle as extern "C" fn(libc::c_int) as libc::sighandler_t,
+                );
+            }
+        }
+    }
+    &SIGNALLED
+}
+
+/// Run `cmd` (for `task`, reading `stdin`), then restart it whenever a file under `root`
+/// matching `patterns` changes.
+///
+/// An empty `patterns` list watches everything. Paths ignored by `root/.gitignore` never
+/// trigger a rerun. Returns once `stop` is set.
+pub fn run(
+    task: &str,
+    cmd: &str,
+    stdin: &Stdin,
+    root: &Path,
+    patterns: &[String],
+    stop: &AtomicBool,
+) -> Result<(), YallaError> {
+    let root = root.canonicalize().map_err(|source| YallaError::Io {
+        context: format!("resolving {}", root.display()),
+        source,
+    })?;
+    let filter = Filter::new(&root, patterns)?;
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |res| {
+        let _ = tx.send(res);
+    })
+    .map_err(watch_error)?;
+    watcher
+        .watch(&root, RecursiveMode::Recursive)
+        .map_err(watch_error)?;
+
+    // Kept after it exits, as processes it left in its group are only killed on a restart
+    let mut child = process::spawn(task, cmd, stdin)?;
+    let mut finished = false;
+    while !stop.load(Ordering::SeqCst) {
+        match rx.recv_timeout(POLL) {
+            Ok(Ok(event)) if filter.wants(&event) => {
+                // Debounce: wait until relevant changes stop arriving before restarting
+                let mut quiet_at = Instant::now() + DEBOUNCE;
+                while let Some(left) = quiet_at.checked_duration_since(Instant::now()) {
+                    if let Ok(Ok(event)) = rx.recv_timeout(left)
+                        && filter.wants(&event)
+                    {
+                        quiet_at = Instant::now() + DEBOUNCE;
+                    }
+                }
+                process::kill_group(&mut child).map_err(|e| child_error(cmd, e))?;
+                eprintln!("yalla: change detected, restarting `{cmd}`");
+                child = process::spawn(task, cmd, stdin)?;
+                finished = false;
+            }
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => return Err(watch_error(e)),
+            Err(RecvTimeoutError::Timeout) => {
+                if !finished
+                    && let Some(status) = child.try_wait().map_err(|e| child_error(cmd, e))?
+                {
+                    if !status.success() {
+                        eprintln!("yalla: `{cmd}` failed ({status})");
+                    }
+                    eprintln!("yalla: waiting for changes...");
+                    finished = true;
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(YallaError::Watch {
+                    reason: "the file watcher stopped unexpectedly".to_string(),
+                });
+            }
+        }
+    }
+
+    process::kill_group(&mut child).map_err(|e| child_error(cmd, e))?;
+    Ok(())
+}
+
+fn watch_error(e: notify::Error) -> YallaError {
+    YallaError::Watch {
+        reason: e.to_string(),
+    }
+}
+
+fn child_error(cmd: &str, source: std::io::Error) -> YallaError {
+    YallaError::Io {
+        context: format!("running `{cmd}`"),
+        source,
+    }
+}
+
+struct Filter {
+    root: PathBuf,
+    patterns: Vec<Pattern>,
+    gitignore: Gitignore,
+}
+
+impl Filter {
+    fn new(root: &Path, patterns: &[String]) -> Result<Self, YallaError> {
+        let patterns = patterns
+            .iter()
+            .map(|p| {
+                Pattern::new(p).map_err(|e| YallaError::InvalidPattern {
+                    pattern: p.clone(),
+                    reason: e.msg.to_string(),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        // A missing .gitignore just yields an empty matcher
+        let (gitignore, _) = Gitignore::new(root.join(".gitignore"));
+        Ok(Filter {
+            root: root.to_path_buf(),
+            patterns,
+            gitignore,
+        })
+    }
+
+    fn wants(&self, event: &Event) -> bool {
+        matches!(
+            event.kind,
+            EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+        ) && event.paths.iter().any(|p| self.matches(p))
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        let Ok(rel) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        if rel.starts_with(".git") || rel.starts_with(".yalla") {
+            return false;
+        }
+        if self
+            .gitignore
+            .matched_path_or_any_parents(rel, path.is_dir())
+            .is_ignore()
+        {
+            return false;
+        }
+        self.patterns.is_empty() || self.patterns.iter().any(|p| p.matches_path(rel))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::thread;
+
+    use super::*;
+
+    fn wait_for_runs(log: &Path, n: usize) -> usize {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            let runs = fs::read_to_string(log).unwrap_or_default().lines().count();
+            if runs >= n || Instant::now() > deadline {
+                return runs;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    #[test]
+    fn filter_respects_patterns_and_gitignore() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+        let filter = Filter::new(&root, &["src/**".to_string()]).unwrap();
+
+        assert!(filter.matches(&root.join("src/main.rs")));
+        assert!(filter.matches(&root.join("src/a/b.rs")));
+        assert!(!filter.matches(&root.join("README.md")));
+        assert!(!filter.matches(&root.join("src/debug.log")));
+        assert!(!filter.matches(&root.join(".yalla/cache/x")));
+
+        let all = Filter::new(&root, &[]).unwrap();
+        assert!(all.matches(&root.join("README.md")));
+        assert!(!all.matches(&root.join("target/release/yalla")));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn reruns_on_change_and_ignores_gitignored_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        fs::write(root.join(".gitignore"), "ignored.txt\n").unwrap();
+        let log = root.join("runs.log");
+        let cmd = format!("sh -c 'echo run >> {}'", log.display());
+
+        let stop = AtomicBool::new(false);
+        let (initial, changed, ignored) = thread::scope(|s| {
+            let handle = s.spawn(|| {
+                run(
+                    "log",
+                    &cmd,
+                    &Stdin::Inherit,
+                    &root,
+                    &["*.txt".to_string()],
+                    &stop,
+                )
+            });
+
+            let initial = wait_for_runs(&log, 1);
+            // Give the watcher a moment to settle before generating events
+            thread::sleep(Duration::from_millis(300));
+
+            fs::write(root.join("input.txt"), "changed").unwrap();
+            let changed = wait_for_runs(&log, 2);
+
+            fs::write(root.join("ignored.txt"), "changed").unwrap();
+            thread::sleep(Duration::from_millis(800));
+            let ignored = wait_for_runs(&log, 0);
+
+            stop.store(true, Ordering::SeqCst);
+            handle.join().unwrap().unwrap();
+            (initial, changed, ignored)
+        });
+
+        assert_eq!(initial, 1, "initial run");
+        assert_eq!(changed, 2, "rerun after change");
+        assert_eq!(ignored, 2, "gitignored path must not rerun");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn stopping_kills_what_the_task_started() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        let pid_file = root.join("pid");
+        let cmd = format!("sh -c 'sleep 60 & echo $! > {}; wait'", pid_file.display());
+        // Running, and not a zombie waiting to be reaped
+        let alive = |pid: &str| {
+            fs::read_to_string(format!("/proc/{pid}/stat"))
+                .is_ok_and(|stat| !stat.rsplit(')').next().unwrap_or("").starts_with(" Z"))
+        };
+
+        let stop = AtomicBool::new(false);
+        let pid = thread::scope(|s| {
+            let handle = s.spawn(|| {
+                run(
+                    "serve",
+                    &cmd,
+                    &Stdin::Null,
+                    &root,
+                    &["*.txt".to_string()],
+                    &stop,
+                )
+            });
+            let deadline = Instant::now() + Duration::from_secs(10);
+            let pid = loop {
+                let pid = fs::read_to_string(&pid_file).unwrap_or_default();
+                if pid.ends_with('\n') || Instant::now() > deadline {
+                    break pid.trim().to_string();
+                }
+                thread::sleep(Duration::from_millis(50));
+            };
+            stop.store(true, Ordering::SeqCst);
+            handle.join().unwrap().unwrap();
+            pid
+        });
+
+        assert!(!pid.is_empty(), "the task never started its child");
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while alive(&pid) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(50));
+        }
+        assert!(!alive(&pid), "`sleep` outlived the watched task");
+    }
+}
@@ -0,0 +1,72 @@
+use yalla::{Options, YallaError, find_node, load_root, parse_root, run_task, split_path};
+
+// Absolute, as `run_task_reports_outcome` changes the working directory
+const BASIC: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/basic/Yallafile"
+);
+
+/// Loading resolves dotted paths and records where tasks are defined.
+#[test]
+fn load_and_find() {
+    let root = load_root("yalla", BASIC).unwrap();
+    let apply = find_node(&root, &split_path("kube.dev.apply")).unwrap();
+    assert_eq!(
+        apply.cmd.as_deref(),
+        Some("kubectl apply -k k8s/overlays/dev")
+    );
+    let source = apply.source.as_ref().unwrap();
+    assert_eq!((source.file.as_str(), source.line), (BASIC, 44));
+}
+
+#[test]
+fn missing_file_is_not_found() {
+    let err = load_root(
+        "yalla",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/nope/Yallafile"),
+    )
+    .unwrap_err();
+    assert!(matches!(err, YallaError::NotFound { .. }), "{err:?}");
+}
+
+#[test]
+fn bad_toml_is_a_located_parse_error() {
+    let err = parse_root("yalla", "Yallafile", "[build\ncmd = \"x\"\n").unwrap_err();
+    let YallaError::Parse(d) = err else {
+        panic!("expected a parse error, got {err:?}");
+    };
+    assert_eq!(d.location.map(|l| l.line), Some(1));
+}
+
+/// Tasks run to completion and report failures instead of exiting the process, and leave
+/// no history behind unless asked to. Private tasks are refused, as on the command line.
+#[test]
+fn run_task_reports_outcome() {
+    let tmp = tempfile::tempdir().unwrap();
+    std::env::set_current_dir(tmp.path()).unwrap();
+    let root = parse_root(
+        "yalla",
+        "Yallafile",
+        "[ok]\ncmd = \"true\"\n[fail]\ncmd = \"false\"\n[ns.child]\ncmd = \"true\"\n\
+         [sign]\ncmd = \"true\"\nprivate = true\n",
+    )
+    .unwrap();
+    let run = |path: &str| run_task(&root, &split_path(path), &[], Options::default());
+
+    run("ok").unwrap();
+    // Unless this test itself runs under a yalla task
+    if std::env::var_os("YALLA_TASK").is_none() {
+        assert!(matches!(run("sign"), Err(YallaError::Private { .. })));
+    }
+    let err = run("fail").unwrap_err();
+    assert!(matches!(&err, YallaError::Exit { task, code: 1 } if task == "fail"));
+    assert_eq!(err.exit_code(), 1);
+    assert!(matches!(run("ns"), Err(YallaError::NotRunnable { .. })));
+    assert!(matches!(run("nope"), Err(YallaError::UnknownTask { .. })));
+    assert!(!tmp.path().join(".yalla").exists());
+
+    let mut opts = Options::default();
+    opts.history = true;
+    run_task(&root, &split_path("ok"), &[], opts).unwrap();
+    assert!(tmp.path().join(".yalla/history.jsonl").exists());
+}
@@ -1,4 +1,7 @@
 #[test]
 fn cli_suite() {
-    trycmd::TestCases::new().case("tests/cmd/*.toml");
+    // Many cases run in checked-in fixture directories, which should stay as they are
+    trycmd::TestCases::new()
+        .env("YALLA_NO_HISTORY", "1")
+        .case("tests/cmd/*.toml");
 }
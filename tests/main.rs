@@ -2,22 +2,26 @@ use snapbox::cargo_bin;
 use snapbox::cmd::Command;
 use tempfile::tempdir;
 
-/// Root (no args): clap shows help on stderr and exits with code 2.
+/// Root (no args): clap shows help on stderr and exits with code 64 (usage).
 #[test]
 fn root_help() {
     let output = Command::new(cargo_bin!("yalla"))
         .current_dir("tests/fixtures/basic")
+        .env("YALLA_NO_HISTORY", "1")
         .output()
         .unwrap();
 
     assert_eq!(
         output.status.code(),
-        Some(2),
+        Some(64),
         "root should require a subcommand"
     );
 
     let err = String::from_utf8_lossy(&output.stderr);
-    assert!(err.contains("Usage: yalla <COMMAND>"), "stderr:\n{err}");
+    assert!(
+        err.contains("Usage: yalla [OPTIONS] <COMMAND>"),
+        "stderr:\n{err}"
+    );
     assert!(
         err.contains("script  Project scripts (also runnable)"),
         "stderr:\n{err}"
@@ -25,16 +29,17 @@ fn root_help() {
     assert!(err.contains("tools   Developer tooling"), "stderr:\n{err}");
 }
 
-/// Namespace without child: `yalla ci` prints its own help and exits 2.
+/// Namespace without child: `yalla ci` prints its own help and exits 64.
 #[test]
 fn namespace_help() {
     let output = Command::new(cargo_bin!("yalla"))
         .current_dir("tests/fixtures/basic")
+        .env("YALLA_NO_HISTORY", "1")
         .args(["ci"])
         .output()
         .unwrap();
 
-    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(output.status.code(), Some(64));
     let err = String::from_utf8_lossy(&output.stderr);
     assert!(err.contains("Usage: yalla ci <COMMAND>"), "stderr:\n{err}");
     assert!(err.contains("build"), "stderr:\n{err}");
@@ -46,6 +51,7 @@ fn namespace_help() {
 fn leaf_exec_tools_ls() {
     let output = Command::new(cargo_bin!("yalla"))
         .current_dir("tests/fixtures/basic")
+        .env("YALLA_NO_HISTORY", "1")
         .args(["tools", "ls"])
         .output()
         .unwrap();
@@ -63,14 +69,15 @@ fn leaf_exec_tools_ls() {
 fn typo_suggestion() {
     let output = Command::new(cargo_bin!("yalla"))
         .current_dir("tests/fixtures/basic")
-        .args(["ci", "buil"])
+        .env("YALLA_NO_HISTORY", "1")
+        .args(["ci", "biuld"])
         .output()
         .unwrap();
 
-    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(output.status.code(), Some(64));
     let err = String::from_utf8_lossy(&output.stderr);
     assert!(
-        err.contains("unrecognized subcommand 'buil'"),
+        err.contains("unrecognized subcommand 'biuld'"),
         "stderr:\n{err}"
     );
     assert!(
@@ -102,19 +109,710 @@ fn yallafile_missing_is_noop() {
     );
 }
 
-/// Unknown top-level subcommand errors (clap), code 2.
+/// Unknown top-level subcommand errors (clap), code 64.
 #[test]
 fn unknown_top_level() {
     let output = Command::new(cargo_bin!("yalla"))
         .current_dir("tests/fixtures/basic")
+        .env("YALLA_NO_HISTORY", "1")
         .args(["nope"])
         .output()
         .unwrap();
 
-    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(output.status.code(), Some(64));
     let err = String::from_utf8_lossy(&output.stderr);
     assert!(
         err.contains("unrecognized subcommand 'nope'"),
         "stderr:\n{err}"
     );
 }
+
+/// Incremental tasks: a hash match skips the run, a source change or `--force` re-runs it.
+#[test]
+fn incremental_skips_unchanged_sources() {
+    let tmp = tempdir().unwrap();
+    std::fs::write(
+        tmp.path().join("Yallafile"),
+        "[gen]\ncmd = \"echo generated\"\nsources = [\"*.txt\"]\n",
+    )
+    .unwrap();
+    std::fs::write(tmp.path().join("input.txt"), "one").unwrap();
+
+    let run = |args: &[&str]| {
+        Command::new(cargo_bin!("yalla"))
+            .current_dir(tmp.path())
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    let first = run(&["gen"]);
+    assert!(first.status.success(), "status: {:?}", first.status);
+    assert_eq!(String::from_utf8_lossy(&first.stdout), "generated\n");
+
+    let second = run(&["-v", "gen"]);
+    assert!(second.status.success());
+    assert!(second.stdout.is_empty(), "task should have been skipped");
+    let err = String::from_utf8_lossy(&second.stderr);
+    assert!(
+        err.contains("skipping gen: sources unchanged since last successful run"),
+        "stderr:\n{err}"
+    );
+
+    let forced = run(&["--force", "gen"]);
+    assert_eq!(String::from_utf8_lossy(&forced.stdout), "generated\n");
+
+    std::fs::write(tmp.path().join("input.txt"), "two").unwrap();
+    let changed = run(&["-v", "gen"]);
+    assert_eq!(String::from_utf8_lossy(&changed.stdout), "generated\n");
+    let err = String::from_utf8_lossy(&changed.stderr);
+    assert!(
+        err.contains("running gen: sources or command changed since last successful run"),
+        "stderr:\n{err}"
+    );
+
+    // Different arguments make a different command, which has not run yet
+    let with_args = run(&["-v", "gen", "--", "--release"]);
+    assert_eq!(
+        String::from_utf8_lossy(&with_args.stdout),
+        "generated --release\n"
+    );
+    let again = run(&["-v", "gen", "--", "--release"]);
+    assert!(again.stdout.is_empty(), "task should have been skipped");
+}
+
+/// A `sources` pattern that matches nothing never lets the task be skipped.
+#[test]
+fn incremental_runs_when_a_source_pattern_matches_nothing() {
+    let tmp = tempdir().unwrap();
+    std::fs::write(
+        tmp.path().join("Yallafile"),
+        "[gen]\ncmd = \"echo generated\"\nsources = [\"nothing/*.zz\"]\n",
+    )
+    .unwrap();
+
+    for _ in 0..2 {
+        let out = Command::new(cargo_bin!("yalla"))
+            .current_dir(tmp.path())
+            .args(["-v", "gen"])
+            .output()
+            .unwrap();
+        assert!(out.status.success(), "status: {:?}", out.status);
+        assert_eq!(String::from_utf8_lossy(&out.stdout), "generated\n");
+        let err = String::from_utf8_lossy(&out.stderr);
+        assert!(
+            err.contains("running gen: source 'nothing/*.zz' matches no files"),
+            "stderr:\n{err}"
+        );
+    }
+}
+
+/// A cache that cannot be written is a warning, not a failure of the task that just succeeded.
+#[test]
+fn incremental_cache_write_failure_only_warns() {
+    let tmp = tempdir().unwrap();
+    std::fs::write(
+        tmp.path().join("Yallafile"),
+        "[gen]\ncmd = \"echo generated\"\nsources = [\"Yallafile\"]\n",
+    )
+    .unwrap();
+    // A file where the cache directory should be
+    std::fs::create_dir(tmp.path().join(".yalla")).unwrap();
+    std::fs::write(tmp.path().join(".yalla/cache"), "").unwrap();
+
+    let out = Command::new(cargo_bin!("yalla"))
+        .current_dir(tmp.path())
+        .arg("gen")
+        .output()
+        .unwrap();
+    assert!(out.status.success(), "status: {:?}", out.status);
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "generated\n");
+    let err = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        err.contains("yalla: cache not updated for gen: creating .yalla/cache"),
+        "stderr:\n{err}"
+    );
+}
+
+/// Incremental tasks: outputs newer than sources skip the run; a missing output forces it.
+#[test]
+fn incremental_skips_when_outputs_are_newer() {
+    let tmp = tempdir().unwrap();
+    std::fs::write(
+        tmp.path().join("Yallafile"),
+        "[build]\ncmd = \"echo built\"\nsources = [\"src.txt\"]\noutputs = [\"out.txt\"]\n",
+    )
+    .unwrap();
+    std::fs::write(tmp.path().join("src.txt"), "source").unwrap();
+
+    let run = || {
+        Command::new(cargo_bin!("yalla"))
+            .current_dir(tmp.path())
+            .args(["-v", "build"])
+            .output()
+            .unwrap()
+    };
+
+    let missing = run();
+    assert_eq!(String::from_utf8_lossy(&missing.stdout), "built\n");
+    let err = String::from_utf8_lossy(&missing.stderr);
+    assert!(
+        err.contains("output 'out.txt' is missing"),
+        "stderr:\n{err}"
+    );
+
+    std::fs::write(tmp.path().join("out.txt"), "artifact").unwrap();
+    let fresh = run();
+    assert!(fresh.stdout.is_empty(), "task should have been skipped");
+    let err = String::from_utf8_lossy(&fresh.stderr);
+    assert!(
+        err.contains("skipping build: outputs are newer than sources"),
+        "stderr:\n{err}"
+    );
+
+    let with_args = Command::new(cargo_bin!("yalla"))
+        .current_dir(tmp.path())
+        .args(["build", "--", "--release"])
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&with_args.stdout),
+        "built --release\n"
+    );
+}
+
+/// Built-ins that don't need tasks work without a Yallafile.
+#[test]
+fn schema_without_yallafile() {
+    let tmp = tempdir().unwrap();
+
+    let output = Command::new(cargo_bin!("yalla"))
+        .current_dir(tmp.path())
+        .args([":schema"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "status: {:?}", output.status);
+    let out = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        out,
+        std::fs::read_to_string("schema/yallafile.schema.json").unwrap()
+    );
+}
+
+/// Dynamic completion: the shell calls back into yalla, which reads the current Yallafile.
+#[test]
+fn completes_task_names_and_choices() {
+    let complete = |words: &[&str]| {
+        let output = Command::new(cargo_bin!("yalla"))
+            .current_dir("tests/fixtures/completions")
+            .env("YALLA_NO_HISTORY", "1")
+            .env("YALLA_NO_HISTORY", "1")
+            .env("COMPLETE", "fish")
+            .arg("--")
+            .args(words)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "status: {:?}", output.status);
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    let out = complete(&["yalla", "d"]);
+    assert!(out.contains("db\n"), "stdout:\n{out}");
+    assert!(
+        out.contains("deploy\tDeploy to an environment"),
+        "stdout:\n{out}"
+    );
+
+    let out = complete(&["yalla", "db", ""]);
+    assert!(out.contains("migrate\tRun migrations"), "stdout:\n{out}");
+
+    let out = complete(&["yalla", "deploy", "s"]);
+    assert_eq!(out, "staging\n");
+}
+
+/// `:completions <shell>` prints a script that calls back into this yalla binary.
+#[test]
+fn completions_script_calls_back() {
+    let output = Command::new(cargo_bin!("yalla"))
+        .current_dir("tests/fixtures/completions")
+        .env("YALLA_NO_HISTORY", "1")
+        .args([":completions", "bash"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "status: {:?}", output.status);
+    let out = String::from_utf8_lossy(&output.stdout);
+    assert!(out.contains("COMPLETE=\"bash\""), "stdout:\n{out}");
+    assert!(out.contains("complete -o nospace"), "stdout:\n{out}");
+    // The absolute path, so the script works from any directory
+    let bin = std::fs::canonicalize(cargo_bin!("yalla")).unwrap();
+    assert!(out.contains(&*bin.to_string_lossy()), "stdout:\n{out}");
+}
+
+/// `:docs --check` passes on freshly generated docs and fails once the Yallafile changes.
+#[test]
+fn docs_check_detects_stale_file() {
+    let tmp = tempdir().unwrap();
+    let yallafile = tmp.path().join("Yallafile");
+    std::fs::write(&yallafile, "[build]\ncmd = \"cargo build\"\n").unwrap();
+
+    let docs = |args: &[&str]| {
+        Command::new(cargo_bin!("yalla"))
+            .current_dir(tmp.path())
+            .env_remove("RUST_BACKTRACE")
+            .env_remove("RUST_LIB_BACKTRACE")
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    let output = docs(&[":docs"]);
+    assert!(output.status.success(), "status: {:?}", output.status);
+    std::fs::write(tmp.path().join("TASKS.md"), &output.stdout).unwrap();
+    assert!(docs(&[":docs", "--check", "TASKS.md"]).status.success());
+
+    std::fs::write(&yallafile, "[build]\ncmd = \"cargo build --release\"\n").unwrap();
+    let output = docs(&[":docs", "--check", "TASKS.md"]);
+    assert!(!output.status.success());
+    let err = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        err.contains(
+            "TASKS.md is stale; regenerate it with `yalla :docs --format markdown > TASKS.md`"
+        ),
+        "stderr:\n{err}"
+    );
+}
+
+/// Runs land in `.yalla/history.jsonl`; `:history` lists them and `--rerun` replays one.
+#[test]
+fn history_records_and_reruns() {
+    let tmp = tempdir().unwrap();
+    std::fs::write(
+        tmp.path().join("Yallafile"),
+        "[greet]\ncmd = \"echo hello\"\n[fail]\ncmd = \"sh -c 'exit 3'\"\n",
+    )
+    .unwrap();
+
+    let run = |args: &[&str]| {
+        Command::new(cargo_bin!("yalla"))
+            .current_dir(tmp.path())
+            .env_remove("YALLA_NO_HISTORY")
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    assert!(run(&["greet", "--", "world"]).status.success());
+    assert_eq!(run(&["fail"]).status.code(), Some(3));
+
+    let log = std::fs::read_to_string(tmp.path().join(".yalla/history.jsonl")).unwrap();
+    let runs: Vec<serde_json::Value> = log
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(runs.len(), 2);
+    assert_eq!(runs[0]["task"], "greet");
+    assert_eq!(
+        runs[0]["argv"],
+        serde_json::json!(["echo", "hello", "world"])
+    );
+    assert_eq!(runs[1]["exit_code"], 3);
+
+    let listed = run(&[":history"]);
+    let out = String::from_utf8_lossy(&listed.stdout);
+    let rows: Vec<&str> = out.lines().collect();
+    assert_eq!(rows.len(), 3, "stdout:\n{out}");
+    assert!(
+        rows[1].starts_with("  1  ") && rows[1].ends_with("  fail"),
+        "stdout:\n{out}"
+    );
+    assert!(rows[2].ends_with("  greet world"), "stdout:\n{out}");
+
+    let rerun = run(&[":history", "--rerun", "2"]);
+    assert!(rerun.status.success(), "status: {:?}", rerun.status);
+    assert_eq!(String::from_utf8_lossy(&rerun.stdout), "hello world\n");
+    assert!(String::from_utf8_lossy(&rerun.stderr).contains("rerunning greet"));
+
+    // YALLA_NO_HISTORY turns recording off
+    let quiet = Command::new(cargo_bin!("yalla"))
+        .current_dir(tmp.path())
+        .env("YALLA_NO_HISTORY", "1")
+        .arg("greet")
+        .output()
+        .unwrap();
+    assert!(quiet.status.success());
+    let log = std::fs::read_to_string(tmp.path().join(".yalla/history.jsonl")).unwrap();
+    assert_eq!(log.lines().count(), 3);
+}
+
+#[test]
+fn time_and_report_describe_the_run() {
+    let tmp = tempdir().unwrap();
+    std::fs::write(
+        tmp.path().join("Yallafile"),
+        "[greet]\ncmd = \"echo hello\"\ntime = true\n[ci.fail]\ncmd = \"sh -c 'exit 3'\"\n\
+         [ci.color]\ncmd = \"\\u001b[31mno-such-tool\\u001b[0m\"\n",
+    )
+    .unwrap();
+
+    // `time = true` in the Yallafile stands in for `--time`
+    let out = Command::new(cargo_bin!("yalla"))
+        .current_dir(tmp.path())
+        .arg("greet")
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    let rows: Vec<&str> = stderr.lines().collect();
+    assert_eq!(rows[0], "TASK   STATUS  TOOK", "stderr:\n{stderr}");
+    assert!(rows[1].starts_with("greet  ok      "), "stderr:\n{stderr}");
+
+    let out = Command::new(cargo_bin!("yalla"))
+        .current_dir(tmp.path())
+        .args(["--report", "ci.xml", "ci", "fail"])
+        .output()
+        .unwrap();
+    assert_eq!(out.status.code(), Some(3));
+    let xml = std::fs::read_to_string(tmp.path().join("ci.xml")).unwrap();
+    assert!(
+        xml.contains(r#"tests="1" failures="1" skipped="0""#),
+        "{xml}"
+    );
+    assert!(
+        xml.contains(r#"<failure message="ci.fail failed with exit code 3"/>"#),
+        "{xml}"
+    );
+
+    // Color codes in messages would make the XML invalid
+    Command::new(cargo_bin!("yalla"))
+        .current_dir(tmp.path())
+        .args(["--report", "color.xml", "ci", "color"])
+        .assert()
+        .code(127);
+    let xml = std::fs::read_to_string(tmp.path().join("color.xml")).unwrap();
+    assert!(
+        xml.contains("program &apos;no-such-tool&apos; not found"),
+        "{xml}"
+    );
+    assert!(!xml.contains('\x1b'), "{xml:?}");
+}
+
+#[test]
+fn log_copies_output_and_is_named_on_failure() {
+    let tmp = tempdir().unwrap();
+    std::fs::write(
+        tmp.path().join("Yallafile"),
+        "[greet]\ncmd = \"sh -c 'echo out; echo err >&2'\"\nlog = \"logs/{{task}}.log\"\n\
+         [fail]\ncmd = \"sh -c 'echo oops; exit 3'\"\n",
+    )
+    .unwrap();
+
+    let out = Command::new(cargo_bin!("yalla"))
+        .current_dir(tmp.path())
+        .arg("greet")
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "out\n");
+    assert_eq!(String::from_utf8_lossy(&out.stderr), "err\n");
+    let log = std::fs::read_to_string(tmp.path().join("logs/greet.log")).unwrap();
+    assert!(log.contains("out\n") && log.contains("err\n"), "{log}");
+
+    let out = Command::new(cargo_bin!("yalla"))
+        .current_dir(tmp.path())
+        .args(["--log-dir", "ci-logs", "fail"])
+        .output()
+        .unwrap();
+    assert_eq!(out.status.code(), Some(3));
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "oops\n");
+    let logs: Vec<_> = std::fs::read_dir(tmp.path().join("ci-logs"))
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    assert_eq!(logs.len(), 1);
+    let name = logs[0].file_name().unwrap().to_string_lossy().into_owned();
+    assert!(
+        name.starts_with("fail-") && name.ends_with("Z.log"),
+        "{name}"
+    );
+    assert_eq!(std::fs::read_to_string(&logs[0]).unwrap(), "oops\n");
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        stderr.contains(&format!("output of fail saved to ci-logs/{name}")),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn output_modes_prefix_or_group_task_output() {
+    let tmp = tempdir().unwrap();
+    std::fs::write(
+        tmp.path().join("Yallafile"),
+        "[ci.test]\ncmd = \"sh -c 'echo one; echo two >&2'\"\n",
+    )
+    .unwrap();
+
+    let run = |mode: &str| {
+        Command::new(cargo_bin!("yalla"))
+            .current_dir(tmp.path())
+            .env_remove("GITLAB_CI")
+            .args(["--output", mode, "ci", "test"])
+            .output()
+            .unwrap()
+    };
+
+    let out = run("prefixed");
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "[ci.test] one\n");
+    assert_eq!(String::from_utf8_lossy(&out.stderr), "[ci.test] two\n");
+
+    let out = run("grouped");
+    assert!(out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.starts_with("::group::ci.test\n"), "{stdout}");
+    assert!(stdout.ends_with("::endgroup::\n"), "{stdout}");
+    assert!(
+        stdout.contains("one\n") && stdout.contains("two\n"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn stdin_comes_from_text_file_or_nothing() {
+    let tmp = tempdir().unwrap();
+    std::fs::write(tmp.path().join("input.txt"), "from file\n").unwrap();
+    std::fs::write(
+        tmp.path().join("Yallafile"),
+        "[text]\ncmd = \"cat\"\nstdin = { text = \"from text\\n\" }\n\
+         [file]\ncmd = \"cat\"\nstdin = { file = \"input.txt\" }\n\
+         [null]\ncmd = \"cat\"\nstdin = \"null\"\n\
+         [inherit]\ncmd = \"cat\"\n",
+    )
+    .unwrap();
+
+    let run = |task: &str| {
+        Command::new(cargo_bin!("yalla"))
+            .current_dir(tmp.path())
+            .arg(task)
+            .stdin("from yalla\n")
+            .output()
+            .unwrap()
+    };
+
+    for (task, expected) in [
+        ("text", "from text\n"),
+        ("file", "from file\n"),
+        ("null", ""),
+        ("inherit", "from yalla\n"),
+    ] {
+        let out = run(task);
+        assert!(out.status.success(), "{task}: {:?}", out.status);
+        assert_eq!(String::from_utf8_lossy(&out.stdout), expected, "{task}");
+    }
+}
+
+#[test]
+fn timeout_kills_a_task_that_runs_too_long() {
+    let tmp = tempdir().unwrap();
+    std::fs::write(
+        tmp.path().join("Yallafile"),
+        "[slow]\ncmd = \"sleep 30\"\ntimeout = 1\n\
+         [quick]\ncmd = \"echo done\"\ntimeout = 30\n",
+    )
+    .unwrap();
+
+    for output in ["raw", "prefixed"] {
+        let started = std::time::Instant::now();
+        let out = Command::new(cargo_bin!("yalla"))
+            .current_dir(tmp.path())
+            .env("YALLA_NO_HISTORY", "1")
+            .args(["--output", output, "slow"])
+            .output()
+            .unwrap();
+        assert_eq!(out.status.code(), Some(124), "{output}");
+        assert!(
+            String::from_utf8_lossy(&out.stderr).contains("slow timed out after 1s"),
+            "{output}: {}",
+            String::from_utf8_lossy(&out.stderr)
+        );
+        assert!(started.elapsed().as_secs() < 10, "{output}: not killed");
+    }
+
+    Command::new(cargo_bin!("yalla"))
+        .current_dir(tmp.path())
+        .env("YALLA_NO_HISTORY", "1")
+        .arg("quick")
+        .assert()
+        .success()
+        .stdout_eq("done\n");
+}
+
+/// `watch` patterns only matter with `--watch`; without it the task runs once and exits.
+#[test]
+fn watch_patterns_alone_run_once() {
+    let tmp = tempdir().unwrap();
+    std::fs::write(
+        tmp.path().join("Yallafile"),
+        "[dev]\ncmd = \"echo once\"\nwatch = [\"*.txt\"]\n",
+    )
+    .unwrap();
+
+    let mut child = std::process::Command::new(cargo_bin!("yalla"))
+        .current_dir(tmp.path())
+        .arg("dev")
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+    while child.try_wait().unwrap().is_none() {
+        if std::time::Instant::now() > deadline {
+            child.kill().unwrap();
+            panic!("yalla dev is still running: it started watching");
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    let out = child.wait_with_output().unwrap();
+    assert!(out.status.success(), "status: {:?}", out.status);
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "once\n");
+}
+
+/// `--watch` gives the task the `stdin` it sets, like a plain run.
+#[test]
+fn watch_passes_stdin_to_the_task() {
+    use std::io::BufRead;
+
+    let tmp = tempdir().unwrap();
+    std::fs::write(
+        tmp.path().join("Yallafile"),
+        "[dev]\ncmd = \"cat\"\nstdin = { text = \"from yalla\\n\" }\nwatch = [\"*.txt\"]\n",
+    )
+    .unwrap();
+
+    let mut child = std::process::Command::new(cargo_bin!("yalla"))
+        .current_dir(tmp.path())
+        .args(["--watch", "dev"])
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    let stdout = child.stdout.take().unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        for line in std::io::BufReader::new(stdout).lines() {
+            let _ = tx.send(line.unwrap());
+        }
+    });
+    let line = rx.recv_timeout(std::time::Duration::from_secs(10));
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert_eq!(line.as_deref(), Ok("from yalla"));
+}
+
+/// `:history --rerun` refuses tasks that are private, even if they were recorded.
+#[test]
+fn history_rerun_refuses_private_tasks() {
+    let tmp = tempdir().unwrap();
+    std::fs::write(
+        tmp.path().join("Yallafile"),
+        "[secret]\ncmd = \"echo leaked\"\nprivate = true\n",
+    )
+    .unwrap();
+    std::fs::create_dir(tmp.path().join(".yalla")).unwrap();
+    std::fs::write(
+        tmp.path().join(".yalla/history.jsonl"),
+        r#"{"task":"secret","args":[],"argv":["echo","leaked"],"cwd":"/","started":"2026-10-18T09:30:00Z","finished":"2026-10-18T09:30:00Z","duration_ms":1,"exit_code":0,"git_rev":null}
+"#,
+    )
+    .unwrap();
+
+    let out = Command::new(cargo_bin!("yalla"))
+        .current_dir(tmp.path())
+        .env_remove("YALLA_TASK")
+        .args([":history", "--rerun", "1"])
+        .output()
+        .unwrap();
+    assert_eq!(out.status.code(), Some(64));
+    assert!(out.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("Task 'secret' is private"));
+}
+
+/// A private task is refused on the command line but runs when another task calls yalla.
+#[test]
+fn private_tasks_run_only_from_other_tasks() {
+    let tmp = tempdir().unwrap();
+    let bin = cargo_bin!("yalla");
+    std::fs::write(
+        tmp.path().join("Yallafile"),
+        format!(
+            "[release]\ncmd = \"'{}' sign\"\n[sign]\ncmd = \"echo signed\"\nprivate = true\n",
+            bin.display()
+        ),
+    )
+    .unwrap();
+    let yalla = |task: &str| {
+        Command::new(bin)
+            .current_dir(tmp.path())
+            .env("YALLA_NO_HISTORY", "1")
+            .env_remove("YALLA_TASK")
+            .arg(task)
+    };
+
+    yalla("sign").assert().code(64).stdout_eq("");
+    yalla("release").assert().success().stdout_eq("signed\n");
+}
+
+/// The picker reads only its two lines; the rest of stdin goes to the picked task.
+#[test]
+fn pick_leaves_the_rest_of_stdin_to_the_task() {
+    let tmp = tempdir().unwrap();
+    std::fs::write(
+        tmp.path().join("Yallafile"),
+        "[echo-input]\ncmd = \"cat\"\n",
+    )
+    .unwrap();
+
+    Command::new(cargo_bin!("yalla"))
+        .current_dir(tmp.path())
+        .env("YALLA_NO_HISTORY", "1")
+        .arg("-i")
+        .stdin("echo\n\nfor the task\n")
+        .assert()
+        .success()
+        .stdout_eq("for the task\n");
+}
+
+/// `--watch` cannot log or reformat output, so it refuses instead of ignoring those options.
+#[test]
+fn watch_refuses_logs_and_output_modes() {
+    let tmp = tempdir().unwrap();
+    std::fs::write(
+        tmp.path().join("Yallafile"),
+        "[dev]\ncmd = \"echo dev\"\n[logged]\ncmd = \"echo logged\"\nlog = \"logged.log\"\n",
+    )
+    .unwrap();
+
+    for (args, option) in [
+        (&["--watch", "logged"][..], "its `log`"),
+        (&["--watch", "--log-dir", "logs", "dev"], "--log-dir"),
+        (
+            &["--watch", "--output", "prefixed", "dev"],
+            "--output prefixed",
+        ),
+    ] {
+        let out = Command::new(cargo_bin!("yalla"))
+            .current_dir(tmp.path())
+            .env("YALLA_NO_HISTORY", "1")
+            .args(args)
+            .output()
+            .unwrap();
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        assert_eq!(out.status.code(), Some(64), "{args:?}: {stderr}");
+        assert!(out.stdout.is_empty(), "{args:?} ran the task");
+        assert!(
+            stderr.contains(&format!("with {option}:")),
+            "{args:?}: {stderr}"
+        );
+    }
+    assert!(!tmp.path().join("logs").exists());
+}